@@ -0,0 +1,301 @@
+use serde::Serialize;
+
+use crate::card::xml_escape;
+use crate::github::Stats;
+use crate::stats::format_number;
+use crate::themes::Theme;
+
+const BADGE_HEIGHT: f64 = 20.0;
+/// Horizontal padding on each side of a badge segment's text.
+const SEGMENT_PADDING: f64 = 6.0;
+
+/// A metric that can be rendered as a small inline badge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    Stars,
+    Commits,
+    TopLanguage,
+}
+
+impl Metric {
+    pub const ALL: [Metric; 3] = [Metric::Stars, Metric::Commits, Metric::TopLanguage];
+
+    /// Parses the `{metric}` path segment of `/badge/{metric}`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "stars" => Some(Metric::Stars),
+            "commits" => Some(Metric::Commits),
+            "top-language" => Some(Metric::TopLanguage),
+            _ => None,
+        }
+    }
+
+    /// The `{metric}` path segment, the inverse of [`Metric::from_name`].
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::Stars => "stars",
+            Metric::Commits => "commits",
+            Metric::TopLanguage => "top-language",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Metric::Stars => "stars",
+            Metric::Commits => "commits",
+            Metric::TopLanguage => "top language",
+        }
+    }
+}
+
+pub struct Badge {
+    pub label: String,
+    pub message: String,
+    pub color: String,
+    pub label_color: String,
+}
+
+impl Badge {
+    pub fn for_metric(metric: Metric, stats: &Stats, theme: &Theme) -> Self {
+        let (message, color) = match metric {
+            Metric::Stars => (format_number(stats.total_stars), theme.icon_color.clone()),
+            Metric::Commits => (format_number(stats.total_commits), theme.icon_color.clone()),
+            Metric::TopLanguage => stats
+                .languages
+                .values()
+                .max_by(|a, b| a.size.total_cmp(&b.size))
+                .map(|lang| (lang.name.clone(), lang.color.clone()))
+                .unwrap_or_else(|| (String::from("none"), theme.text_color.clone())),
+        };
+        let label = match (metric, stats.commits_year) {
+            (Metric::Commits, Some(year)) => format!("{} {year}", metric.label()),
+            _ => metric.label().to_string(),
+        };
+        Badge {
+            label,
+            message,
+            color,
+            label_color: theme.background_color.clone(),
+        }
+    }
+
+    /// Badge shown in place of a metric when stats could not be fetched.
+    pub fn error(metric: Metric, message: &str, theme: &Theme) -> Self {
+        Badge {
+            label: metric.label().to_string(),
+            message: message.to_string(),
+            color: String::from("#e05d44"),
+            label_color: theme.background_color.clone(),
+        }
+    }
+}
+
+/// Response body for the shields.io "endpoint" badge schema.
+///
+/// See <https://shields.io/badges/endpoint-badge>.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShieldsEndpoint {
+    schema_version: u8,
+    label: String,
+    message: String,
+    color: String,
+    label_color: String,
+    is_error: bool,
+    cache_seconds: u32,
+}
+
+impl ShieldsEndpoint {
+    pub fn new(badge: Badge, is_error: bool, cache_seconds: u32) -> Self {
+        // Shields expects bare hex values; a leading '#' is not accepted everywhere.
+        ShieldsEndpoint {
+            schema_version: 1,
+            label: badge.label,
+            message: badge.message,
+            color: badge.color.trim_start_matches('#').to_string(),
+            label_color: badge.label_color.trim_start_matches('#').to_string(),
+            is_error,
+            cache_seconds,
+        }
+    }
+}
+
+/// Approximates the rendered width of `text` in 11 px Verdana, the font used by flat badges.
+///
+/// Exact glyph metrics are not available without a font file; grouping characters into a
+/// few width classes keeps segments snug enough that text never overflows.
+fn text_width(text: &str) -> f64 {
+    text.chars()
+        .map(|c| match c {
+            'i' | 'j' | 'l' | '.' | ',' | ':' | ';' | '!' | '|' | '\'' => 3.5,
+            ' ' | 'f' | 'r' | 't' | 'I' | '(' | ')' | '[' | ']' => 4.5,
+            'm' | 'w' | 'M' | 'W' => 10.0,
+            c if c.is_ascii_uppercase() => 7.5,
+            c if c.is_ascii_digit() => 7.0,
+            _ => 6.5,
+        })
+        .sum()
+}
+
+/// Renders a shields.io-style "flat" badge: a label segment and a colored message segment.
+pub fn render_badge(badge: &Badge, theme: &Theme) -> String {
+    let label_width = (text_width(&badge.label) + 2.0 * SEGMENT_PADDING).round();
+    let message_width = (text_width(&badge.message) + 2.0 * SEGMENT_PADDING).round();
+    let width = label_width + message_width;
+    let height = BADGE_HEIGHT;
+    let label_x = label_width / 2.0;
+    let message_x = label_width + message_width / 2.0;
+    let label = xml_escape(&badge.label);
+    let message = xml_escape(&badge.message);
+    let label_color = &badge.label_color;
+    let color = &badge.color;
+    let text_color = &theme.title_color;
+    format!(
+        r#"<svg width='{width}' height='{height}' viewBox='0 0 {width} {height}' xmlns='http://www.w3.org/2000/svg' role='img' aria-label='{label}: {message}'>
+    <title>{label}: {message}</title>
+    <linearGradient id='badge-shine' x2='0' y2='100%'>
+        <stop offset='0' stop-color='#bbb' stop-opacity='.1'/>
+        <stop offset='1' stop-opacity='.1'/>
+    </linearGradient>
+    <clipPath id='badge-round'>
+        <rect width='{width}' height='{height}' rx='3' fill='#fff'/>
+    </clipPath>
+    <g clip-path='url(#badge-round)'>
+        <rect width='{label_width}' height='{height}' fill='{label_color}'/>
+        <rect x='{label_width}' width='{message_width}' height='{height}' fill='{color}'/>
+        <rect width='{width}' height='{height}' fill='url(#badge-shine)'/>
+    </g>
+    <g text-anchor='middle' style="font: 400 11px Verdana, Geneva, 'DejaVu Sans', sans-serif;">
+        <text x='{label_x}' y='14' fill='{text_color}'>{label}</text>
+        <text x='{message_x}' y='14' fill='#fff' stroke='#0003' stroke-width='0.5' paint-order='stroke'>{message}</text>
+    </g>
+</svg>"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::github::Language;
+    use crate::themes;
+
+    fn make_stats() -> Stats {
+        let mut languages = HashMap::new();
+        languages.insert(
+            "Rust".to_string(),
+            Language {
+                name: "Rust".to_string(),
+                color: "#dea584".to_string(),
+                size: 5000.0,
+            },
+        );
+        languages.insert(
+            "Python".to_string(),
+            Language {
+                name: "Python".to_string(),
+                color: "#3572A5".to_string(),
+                size: 1000.0,
+            },
+        );
+        Stats {
+            total_stars: 1234,
+            total_commits: 42,
+            languages,
+            commits_year: None,
+            includes_private: false,
+            repositories: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn metric_from_name() {
+        assert_eq!(Metric::from_name("stars"), Some(Metric::Stars));
+        assert_eq!(Metric::from_name("commits"), Some(Metric::Commits));
+        assert_eq!(Metric::from_name("top-language"), Some(Metric::TopLanguage));
+        assert_eq!(Metric::from_name("forks"), None);
+    }
+
+    #[test]
+    fn badge_for_stars_formats_number() {
+        let badge = Badge::for_metric(Metric::Stars, &make_stats(), &themes::dark());
+        assert_eq!(badge.label, "stars");
+        assert_eq!(badge.message, "1.2k");
+    }
+
+    #[test]
+    fn badge_for_commits_includes_year() {
+        let mut stats = make_stats();
+        stats.commits_year = Some(2025);
+        let badge = Badge::for_metric(Metric::Commits, &stats, &themes::dark());
+        assert_eq!(badge.label, "commits 2025");
+    }
+
+    #[test]
+    fn badge_for_top_language_uses_language_color() {
+        let badge = Badge::for_metric(Metric::TopLanguage, &make_stats(), &themes::dark());
+        assert_eq!(badge.message, "Rust");
+        assert_eq!(badge.color, "#dea584");
+    }
+
+    #[test]
+    fn badge_for_top_language_without_languages() {
+        let stats = Stats {
+            total_stars: 0,
+            total_commits: 0,
+            languages: HashMap::new(),
+            commits_year: None,
+            includes_private: false,
+            repositories: HashMap::new(),
+        };
+        let badge = Badge::for_metric(Metric::TopLanguage, &stats, &themes::dark());
+        assert_eq!(badge.message, "none");
+    }
+
+    #[test]
+    fn shields_endpoint_serializes_schema() {
+        let badge = Badge::for_metric(Metric::Commits, &make_stats(), &themes::dark());
+        let json = serde_json::to_value(ShieldsEndpoint::new(badge, false, 3600)).unwrap();
+        assert_eq!(json["schemaVersion"], 1);
+        assert_eq!(json["label"], "commits");
+        assert_eq!(json["message"], "42");
+        assert_eq!(json["color"], "79ff97");
+        assert_eq!(json["labelColor"], "151515");
+        assert_eq!(json["isError"], false);
+        assert_eq!(json["cacheSeconds"], 3600);
+    }
+
+    #[test]
+    fn render_badge_produces_svg() {
+        let theme = themes::light();
+        let badge = Badge::for_metric(Metric::Stars, &make_stats(), &theme);
+        let svg = render_badge(&badge, &theme);
+        assert!(svg.contains("<svg"));
+        assert!(svg.contains("</svg>"));
+        assert!(svg.contains("stars"));
+        assert!(svg.contains("1.2k"));
+        assert!(svg.contains(&theme.background_color));
+        assert!(svg.contains(&theme.icon_color));
+    }
+
+    #[test]
+    fn render_badge_escapes_text() {
+        let theme = themes::dark();
+        let badge = Badge {
+            label: "a & b".to_string(),
+            message: "<x>".to_string(),
+            color: "#fff".to_string(),
+            label_color: "#000".to_string(),
+        };
+        let svg = render_badge(&badge, &theme);
+        assert!(!svg.contains("<x>"));
+        assert!(svg.contains("a &amp; b"));
+    }
+
+    #[test]
+    fn text_width_grows_with_length() {
+        assert!(text_width("commits") > text_width("stars"));
+        assert_eq!(text_width(""), 0.0);
+    }
+}
@@ -0,0 +1,451 @@
+use std::fs;
+use std::io::{self, Error, Write};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use log::{error, info};
+
+use crate::logging::LogFormat;
+use crate::{
+    build_combined_svg, build_languages_svg, build_stats_svg, build_window_stats_svg, crawl_stats,
+    crawl_window_stats, github, read_stats_snapshot, site, stats_cache_path, themes,
+    window_cache_path, CardQuery, Config,
+};
+
+/// GitHub stats cards for READMEs, served over HTTP or rendered from the command line.
+///
+/// Configuration is read from an optional TOML or YAML file, overridden by `STATS_*`
+/// environment variables and `.env`.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    /// Config file (TOML or YAML)
+    #[arg(long, global = true, env = "STATS_CONFIG_FILE")]
+    config: Option<PathBuf>,
+    /// Log line format
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        env = "STATS_LOG_FORMAT",
+        default_value_t
+    )]
+    log_format: LogFormat,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run the HTTP server (default)
+    Serve,
+    /// Crawl GitHub and write the stats cache, regardless of its age; with a window, also
+    /// fetch its contribution counts
+    Fetch(WindowArgs),
+    /// Render a card from the stats cache without starting the server
+    Render(RenderArgs),
+    /// Fetch stats once and write every card, badge and JSON file to a directory, e.g. for
+    /// GitHub Pages
+    BuildSite(SiteArgs),
+    /// Check the configuration and exit
+    ValidateConfig,
+}
+
+#[derive(Args, Debug, Default)]
+struct WindowArgs {
+    /// Count contributions in this calendar year
+    #[arg(long, conflicts_with_all = ["since", "until"])]
+    year: Option<i32>,
+    /// Start of the contribution window (YYYY-MM-DD)
+    #[arg(long)]
+    since: Option<String>,
+    /// End of the contribution window (YYYY-MM-DD, default today)
+    #[arg(long, requires = "since")]
+    until: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Card {
+    Stats,
+    Languages,
+    Combined,
+}
+
+#[derive(Args, Debug)]
+struct RenderArgs {
+    #[arg(long, value_enum, default_value_t = Card::Stats)]
+    card: Card,
+    /// Card theme: dark or light
+    #[arg(long)]
+    theme: Option<String>,
+    /// Card width in pixels
+    #[arg(long)]
+    width: Option<f64>,
+    /// Number of languages to show
+    #[arg(long)]
+    lang_count: Option<usize>,
+    /// Mark the title when private repositories are included
+    #[arg(long)]
+    private_marker: bool,
+    /// Window to show contribution counts for; must have been fetched before
+    #[command(flatten)]
+    window: WindowArgs,
+    /// Output file; `-` or omitted writes to stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct SiteArgs {
+    /// Directory to write the site to
+    #[arg(short, long, default_value = "site")]
+    output: PathBuf,
+    /// Themes to render, built-in or from the config file (comma-separated or repeated)
+    #[arg(long = "theme", value_delimiter = ',', default_values = themes::NAMES)]
+    themes: Vec<String>,
+    /// Card widths in pixels (comma-separated or repeated)
+    #[arg(long = "width", value_delimiter = ',', default_values = ["300"])]
+    widths: Vec<f64>,
+    /// Number of languages to show
+    #[arg(long)]
+    lang_count: Option<usize>,
+    /// Mark card titles when private repositories are included
+    #[arg(long)]
+    private_marker: bool,
+    /// Use the cached stats instead of crawling GitHub
+    #[arg(long)]
+    no_fetch: bool,
+}
+
+impl WindowArgs {
+    fn window(&self) -> Result<Option<github::Window>, String> {
+        self.query().window()
+    }
+
+    fn query(&self) -> CardQuery {
+        CardQuery {
+            year: self.year,
+            since: self.since.clone(),
+            until: self.until.clone(),
+            ..CardQuery::default()
+        }
+    }
+}
+
+impl RenderArgs {
+    fn query(&self) -> CardQuery {
+        CardQuery {
+            theme: self.theme.clone(),
+            lang_count: self.lang_count,
+            width: self.width,
+            private_marker: Some(self.private_marker),
+            ..self.window.query()
+        }
+    }
+}
+
+impl Cli {
+    /// Logs of the one-off commands go to stderr so rendered output can be piped.
+    pub fn logs_to_stdout(&self) -> bool {
+        matches!(self.command, None | Some(Command::Serve))
+    }
+
+    pub fn log_format(&self) -> LogFormat {
+        self.log_format
+    }
+
+    pub async fn run(self) -> Result<(), Error> {
+        let config = load_config(self.config.as_deref())?;
+        themes::register(&config.themes);
+        match self.command.unwrap_or(Command::Serve) {
+            Command::Serve => crate::serve(config, self.config).await,
+            Command::Fetch(args) => fetch(&config, &args).await,
+            Command::Render(args) => render(&config, &args),
+            Command::BuildSite(args) => build_site(&config, &args).await,
+            Command::ValidateConfig => validate_config(&config),
+        }
+    }
+}
+
+fn load_config(file: Option<&Path>) -> Result<Config, Error> {
+    Config::load(file).map_err(|err| {
+        error!("Failed to read config: {:?}", err);
+        Error::other(err.to_string())
+    })
+}
+
+/// Logs `err` and turns it into the error `main` exits with.
+fn fail(context: &str, err: impl std::fmt::Display) -> Error {
+    error!("{context}: {err}");
+    Error::other(format!("{context}: {err}"))
+}
+
+/// Crawls GitHub even if the cached stats are still fresh. A failed crawl leaves the
+/// cache as it was.
+async fn fetch_fresh_stats(config: &Config) -> Result<github::Stats, Error> {
+    let path = stats_cache_path(&config.cache_path);
+    let cached = crawl_stats(config)
+        .await
+        .map_err(|err| fail("Failed to fetch GitHub stats", format!("{err:#}")))?;
+    info!(
+        "Fetched stats for {}: {} stars, {} commits, {} languages, written to {}",
+        config.github_user,
+        cached.stats.total_stars,
+        cached.stats.total_commits,
+        cached.stats.languages.len(),
+        path.display()
+    );
+    Ok(cached.stats)
+}
+
+async fn fetch(config: &Config, args: &WindowArgs) -> Result<(), Error> {
+    let window = args.window().map_err(|err| fail("Invalid window", err))?;
+    fetch_fresh_stats(config).await?;
+
+    if let Some(window) = window {
+        let path = window_cache_path(&config.cache_path, &window);
+        let cached = crawl_window_stats(config, &window)
+            .await
+            .map_err(|err| fail("Failed to fetch window stats", format!("{err:#}")))?;
+        info!(
+            "Fetched contributions from {} to {}: {} commits, written to {}",
+            cached.stats.since,
+            cached.stats.until,
+            cached.stats.commits,
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+fn cached_stats(config: &Config) -> Result<github::Stats, String> {
+    let path = stats_cache_path(&config.cache_path);
+    read_stats_snapshot(&path).ok_or_else(|| {
+        format!(
+            "No stats cached at {}; run `github-stats fetch` first",
+            path.display()
+        )
+    })
+}
+
+/// Renders the requested card from whatever is cached, however old.
+fn render_svg(config: &Config, args: &RenderArgs) -> Result<String, String> {
+    let query = args.query();
+    let stats = cached_stats(config)?;
+    let window = match (args.card, query.window()?) {
+        (Card::Languages, _) | (_, None) => None,
+        (_, Some(window)) => {
+            let path = window_cache_path(&config.cache_path, &window);
+            let window_stats: github::WindowStats =
+                read_stats_snapshot(&path).ok_or_else(|| {
+                    format!(
+                        "No contributions cached at {}; run `github-stats fetch` with the same window first",
+                        path.display()
+                    )
+                })?;
+            Some((window_stats, window))
+        }
+    };
+
+    Ok(match (args.card, &window) {
+        (Card::Stats, None) => build_stats_svg(&stats, &query),
+        (Card::Stats, Some((window_stats, window))) => {
+            build_window_stats_svg(window_stats, window, &query)
+        }
+        (Card::Languages, _) => build_languages_svg(&stats, &query),
+        (Card::Combined, window) => build_combined_svg(
+            &stats,
+            window.as_ref().map(|(w, window)| (w, window)),
+            &query,
+        ),
+    })
+}
+
+fn render(config: &Config, args: &RenderArgs) -> Result<(), Error> {
+    let svg = render_svg(config, args).map_err(|err| fail("Failed to render card", err))?;
+    match args.output.as_deref() {
+        Some(path) if path != Path::new("-") => {
+            fs::write(path, svg)?;
+            info!("Wrote {}", path.display());
+        }
+        _ => io::stdout().write_all(svg.as_bytes())?,
+    }
+    Ok(())
+}
+
+async fn build_site(config: &Config, args: &SiteArgs) -> Result<(), Error> {
+    if let Some(unknown) = args.themes.iter().find(|theme| !themes::exists(theme)) {
+        return Err(fail(
+            "Failed to build site",
+            format!("unknown theme '{unknown}'"),
+        ));
+    }
+    let stats = if args.no_fetch {
+        cached_stats(config).map_err(|err| fail("Failed to build site", err))?
+    } else {
+        fetch_fresh_stats(config).await?
+    };
+    let options = site::SiteOptions {
+        themes: &args.themes,
+        widths: &args.widths,
+        lang_count: args.lang_count,
+        private_marker: args.private_marker,
+        cache_seconds: config.cache_seconds,
+    };
+    let written = site::write_site(&args.output, &stats, &options)
+        .map_err(|err| fail("Failed to write site", err))?;
+    info!("Wrote {written} files to {}", args.output.display());
+    Ok(())
+}
+
+/// Settings that parse but would fail at runtime.
+fn config_problems(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+    let addresses = [
+        ("ipv4_address", &config.ipv4_address),
+        ("ipv6_address", &config.ipv6_address),
+    ];
+    for (name, address) in addresses {
+        if !address.is_empty() && address.parse::<IpAddr>().is_err() {
+            problems.push(format!("{name} '{address}' is not an IP address"));
+        }
+    }
+    if addresses.iter().all(|(_, address)| address.is_empty()) {
+        problems.push(String::from("neither ipv4_address nor ipv6_address is set"));
+    }
+    let files = [
+        ("github_app_private_key", &config.github_app_private_key),
+        ("github_ca_cert", &config.github_ca_cert),
+    ];
+    for (name, path) in files {
+        if let Some(path) = path {
+            if let Err(err) = fs::metadata(path) {
+                problems.push(format!("{name} {}: {err}", path.display()));
+            }
+        }
+    }
+    if !config.cache_path.is_empty() && !Path::new(&config.cache_path).is_dir() {
+        problems.push(format!(
+            "cache_path {} is not a directory",
+            config.cache_path
+        ));
+    }
+    problems
+}
+
+fn validate_config(config: &Config) -> Result<(), Error> {
+    let problems = config_problems(config);
+    if problems.is_empty() {
+        println!("Configuration is valid: {config:?}");
+        return Ok(());
+    }
+    for problem in &problems {
+        error!("Invalid configuration: {problem}");
+    }
+    Err(Error::other(format!(
+        "{} configuration problem(s)",
+        problems.len()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+    use crate::invalidate_cached_stats;
+    use crate::test_dir::TestDir;
+
+    fn parse(args: &[&str]) -> Cli {
+        Cli::try_parse_from(std::iter::once("github-stats").chain(args.iter().copied())).unwrap()
+    }
+
+    fn test_config(cache_path: &str) -> Config {
+        Config::for_tests(&[("cache_path", cache_path)])
+    }
+
+    #[test]
+    fn cli_definition_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn no_subcommand_serves() {
+        let cli = parse(&[]);
+        assert!(cli.command.is_none());
+        assert!(cli.logs_to_stdout());
+        assert!(!parse(&["validate-config"]).logs_to_stdout());
+    }
+
+    #[test]
+    fn render_arguments_build_card_query() {
+        let cli = parse(&[
+            "render", "--card", "combined", "--theme", "light", "--year", "2024", "-o", "card.svg",
+        ]);
+        let Some(Command::Render(args)) = cli.command else {
+            panic!("expected render");
+        };
+        assert_eq!(args.card, Card::Combined);
+        assert_eq!(args.output, Some(PathBuf::from("card.svg")));
+        let query = args.query();
+        assert_eq!(query.theme.as_deref(), Some("light"));
+        assert_eq!(query.year, Some(2024));
+    }
+
+    #[test]
+    fn build_site_defaults_to_all_themes() {
+        let Some(Command::BuildSite(args)) = parse(&["build-site", "--width", "300,500"]).command
+        else {
+            panic!("expected build-site");
+        };
+        assert_eq!(args.themes, themes::NAMES);
+        assert_eq!(args.widths, [300.0, 500.0]);
+    }
+
+    #[test]
+    fn year_conflicts_with_since() {
+        let args = [
+            "github-stats",
+            "fetch",
+            "--year",
+            "2024",
+            "--since",
+            "2024-01-01",
+        ];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn render_svg_reads_cache_of_any_age() {
+        let dir = TestDir::new("render-any-age");
+        let config = test_config(dir.path().to_str().unwrap());
+        let Some(Command::Render(args)) = parse(&["render", "--card", "stats"]).command else {
+            panic!("expected render");
+        };
+
+        let path = stats_cache_path(&config.cache_path);
+        let _ = fs::remove_file(&path);
+        assert!(render_svg(&config, &args).unwrap_err().contains("fetch"));
+
+        fs::write(
+            &path,
+            r#"{"total_stars": 4321, "total_commits": 7, "languages": {}}"#,
+        )
+        .unwrap();
+        invalidate_cached_stats(&path).unwrap();
+        assert!(render_svg(&config, &args).unwrap().contains("4.3k"));
+    }
+
+    #[test]
+    fn config_problems_reports_runtime_failures() {
+        let mut config = test_config("/nonexistent/cache");
+        config.ipv4_address = String::from("not-an-ip");
+        config.github_ca_cert = Some(PathBuf::from("/nonexistent/ca.pem"));
+        let problems = config_problems(&config);
+        assert_eq!(problems.len(), 3, "{problems:?}");
+
+        let config = test_config("");
+        assert!(config_problems(&config).is_empty());
+    }
+}
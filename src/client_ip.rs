@@ -0,0 +1,219 @@
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+use actix_web::http::header::{HeaderMap, FORWARDED, X_FORWARDED_FOR};
+
+/// An address or CIDR block, e.g. `10.0.0.0/8`, `2001:db8::/32` or `192.0.2.7`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNetwork {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid IP address or CIDR block `{s}`");
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr.parse().map_err(|_| invalid())?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse()
+                .ok()
+                .filter(|p| *p <= max)
+                .ok_or_else(invalid)?,
+            None => max,
+        };
+        Ok(IpNetwork {
+            addr: addr.to_canonical(),
+            prefix,
+        })
+    }
+}
+
+impl fmt::Display for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+/// Parses a forwarded address: `192.0.2.7`, `192.0.2.7:4711`, `2001:db8::1` or
+/// `"[2001:db8::1]:4711"`. Obfuscated identifiers such as `unknown` yield `None`.
+fn parse_forwarded_addr(value: &str) -> Option<IpAddr> {
+    let value = value.trim().trim_matches('"');
+    value
+        .parse()
+        .ok()
+        .or_else(|| value.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+        .or_else(|| value.strip_prefix('[')?.split(']').next()?.parse().ok())
+}
+
+/// The hops a request passed through, client first, from `Forwarded` or, without it,
+/// `X-Forwarded-For`. `None` marks a hop whose address is unknown.
+fn forwarded_chain(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    let forwarded: Vec<&str> = headers
+        .get_all(FORWARDED)
+        .filter_map(|v| v.to_str().ok())
+        .collect();
+    if !forwarded.is_empty() {
+        return forwarded
+            .iter()
+            .flat_map(|v| v.split(','))
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.split_once('='))
+                    .find(|(name, _)| name.trim().eq_ignore_ascii_case("for"))
+                    .and_then(|(_, value)| parse_forwarded_addr(value))
+            })
+            .collect();
+    }
+    headers
+        .get_all(X_FORWARDED_FOR)
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(parse_forwarded_addr)
+        .collect()
+}
+
+/// The address of the client that sent a request received from `peer`.
+///
+/// Forwarding headers are only believed when `peer` is a trusted proxy. The chain is then
+/// walked back from the nearest hop and the first address that is not a trusted proxy is
+/// the client, so a client cannot pose as another by sending its own header. An unknown
+/// hop stops the walk at the last proxy that is known.
+pub fn client_ip(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpNetwork]) -> IpAddr {
+    let is_trusted = |ip: IpAddr| trusted_proxies.iter().any(|net| net.contains(ip));
+    let mut client = peer.to_canonical();
+    if !is_trusted(client) {
+        return client;
+    }
+    for hop in forwarded_chain(headers).into_iter().rev() {
+        let Some(hop) = hop else { break };
+        client = hop.to_canonical();
+        if !is_trusted(client) {
+            break;
+        }
+    }
+    client
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::header::{HeaderName, HeaderValue};
+
+    use super::*;
+
+    fn networks(list: &[&str]) -> Vec<IpNetwork> {
+        list.iter().map(|n| n.parse().unwrap()).collect()
+    }
+
+    fn headers(pairs: &[(HeaderName, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(name.clone(), HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn networks_match_their_prefix() {
+        let net: IpNetwork = "10.1.0.0/16".parse().unwrap();
+        assert!(net.contains(ip("10.1.200.3")));
+        assert!(!net.contains(ip("10.2.0.1")));
+        assert!(net.contains(ip("::ffff:10.1.0.9")));
+        assert!("0.0.0.0/0"
+            .parse::<IpNetwork>()
+            .unwrap()
+            .contains(ip("8.8.8.8")));
+        assert!("2001:db8::/32"
+            .parse::<IpNetwork>()
+            .unwrap()
+            .contains(ip("2001:db8:1::1")));
+        assert_eq!(
+            "192.0.2.7".parse::<IpNetwork>().unwrap().to_string(),
+            "192.0.2.7/32"
+        );
+        assert!("10.0.0.0/33".parse::<IpNetwork>().is_err());
+        assert!("example.com".parse::<IpNetwork>().is_err());
+    }
+
+    #[test]
+    fn headers_from_untrusted_peers_are_ignored() {
+        let headers = headers(&[(X_FORWARDED_FOR, "203.0.113.9")]);
+        assert_eq!(
+            client_ip(ip("198.51.100.1"), &headers, &[]),
+            ip("198.51.100.1")
+        );
+    }
+
+    #[test]
+    fn chain_is_walked_back_to_the_first_untrusted_hop() {
+        let trusted = networks(&["10.0.0.0/8"]);
+        // The client claimed to be 192.0.2.1; only the hop our proxy saw counts.
+        let headers = headers(&[(X_FORWARDED_FOR, "192.0.2.1, 203.0.113.9, 10.0.0.2")]);
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), &headers, &trusted),
+            ip("203.0.113.9")
+        );
+    }
+
+    #[test]
+    fn forwarded_header_takes_precedence() {
+        let trusted = networks(&["10.0.0.0/8"]);
+        let headers = headers(&[
+            (X_FORWARDED_FOR, "192.0.2.1"),
+            (FORWARDED, r#"for="[2001:db8:cafe::17]:4711";proto=https"#),
+            (FORWARDED, "for=10.0.0.3;by=10.0.0.1"),
+        ]);
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), &headers, &trusted),
+            ip("2001:db8:cafe::17")
+        );
+    }
+
+    #[test]
+    fn unknown_hop_stops_at_the_last_known_proxy() {
+        let trusted = networks(&["10.0.0.0/8"]);
+        let headers = headers(&[(FORWARDED, "for=unknown, for=10.0.0.5")]);
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), &headers, &trusted),
+            ip("10.0.0.5")
+        );
+        let no_headers = HeaderMap::new();
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), &no_headers, &trusted),
+            ip("10.0.0.1")
+        );
+    }
+}
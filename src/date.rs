@@ -0,0 +1,206 @@
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A proleptic Gregorian calendar date in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    pub fn new(year: i32, month: u32, day: u32) -> Option<Self> {
+        let valid = (1..=12).contains(&month) && day >= 1 && day <= days_in_month(year, month);
+        valid.then_some(Date { year, month, day })
+    }
+
+    /// Parses an ISO 8601 calendar date (`YYYY-MM-DD`).
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.splitn(3, '-');
+        let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+        if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+            return None;
+        }
+        let all_digits = [year, month, day]
+            .iter()
+            .all(|p| p.bytes().all(|b| b.is_ascii_digit()));
+        if !all_digits {
+            return None;
+        }
+        Date::new(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)
+    }
+
+    pub fn first_of_year(year: i32) -> Self {
+        Date {
+            year,
+            month: 1,
+            day: 1,
+        }
+    }
+
+    pub fn last_of_year(year: i32) -> Self {
+        Date {
+            year,
+            month: 12,
+            day: 31,
+        }
+    }
+
+    pub fn today() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Date::from_unix_days((secs / 86_400) as i64)
+    }
+
+    /// Converts days since 1970-01-01 into a calendar date.
+    ///
+    /// Uses Howard Hinnant's `civil_from_days` algorithm, which is exact for the whole
+    /// range of `i32` years without any lookup tables.
+    pub fn from_unix_days(days: i64) -> Self {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (yoe + era * 400 + i64::from(month <= 2)) as i32;
+        Date { year, month, day }
+    }
+
+    /// Days since 1970-01-01; the inverse of [`Date::from_unix_days`] (`days_from_civil`).
+    pub fn unix_days(&self) -> i64 {
+        let (month, day) = (i64::from(self.month), i64::from(self.day));
+        let year = i64::from(self.year) - i64::from(month <= 2);
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
+    }
+}
+
+/// Parses a UTC timestamp as returned by the GitHub API (`YYYY-MM-DDTHH:MM:SSZ`).
+pub fn parse_timestamp(s: &str) -> Option<SystemTime> {
+    let (date, time) = s.strip_suffix('Z')?.split_once('T')?;
+    let date = Date::parse(date)?;
+    let mut parts = time.splitn(3, ':');
+    let mut next = |max: u64| {
+        parts
+            .next()
+            .filter(|p| p.len() == 2)
+            .and_then(|p| p.parse::<u64>().ok())
+            .filter(|&v| v <= max)
+    };
+    let (hours, minutes, seconds) = (next(23)?, next(59)?, next(59)?);
+    let days = u64::try_from(date.unix_days()).ok()?;
+    let secs = days * 86_400 + hours * 3600 + minutes * 60 + seconds;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_unix_days_epoch() {
+        assert_eq!(
+            Date::from_unix_days(0),
+            Date {
+                year: 1970,
+                month: 1,
+                day: 1
+            }
+        );
+    }
+
+    #[test]
+    fn from_unix_days_leap_day() {
+        // 2024-02-29 is 19782 days after the epoch.
+        assert_eq!(
+            Date::from_unix_days(19_782),
+            Date {
+                year: 2024,
+                month: 2,
+                day: 29
+            }
+        );
+    }
+
+    #[test]
+    fn parse_valid_date() {
+        assert_eq!(Date::parse("2025-03-09"), Date::new(2025, 3, 9));
+        assert_eq!(Date::parse("2024-02-29").unwrap().to_string(), "2024-02-29");
+    }
+
+    #[test]
+    fn parse_rejects_invalid_dates() {
+        assert_eq!(Date::parse("2025-02-29"), None);
+        assert_eq!(Date::parse("2025-13-01"), None);
+        assert_eq!(Date::parse("2025-1-01"), None);
+        assert_eq!(Date::parse("2025-01-01T00:00:00Z"), None);
+        assert_eq!(Date::parse("+025-01-01"), None);
+        assert_eq!(Date::parse(""), None);
+    }
+
+    #[test]
+    fn leap_years() {
+        assert!(is_leap_year(2024));
+        assert!(is_leap_year(2000));
+        assert!(!is_leap_year(1900));
+        assert!(!is_leap_year(2025));
+    }
+
+    #[test]
+    fn unix_days_round_trips() {
+        for days in [-719_468, -1, 0, 19_782, 20_000, 2_932_896] {
+            assert_eq!(Date::from_unix_days(days).unix_days(), days);
+        }
+    }
+
+    #[test]
+    fn parse_timestamp_utc() {
+        assert_eq!(
+            parse_timestamp("2024-02-29T01:02:03Z"),
+            Some(UNIX_EPOCH + Duration::from_secs(19_782 * 86_400 + 3723))
+        );
+        assert_eq!(parse_timestamp("2024-02-29T01:02:03+01:00"), None);
+        assert_eq!(parse_timestamp("2024-02-29T24:00:00Z"), None);
+        assert_eq!(parse_timestamp("2024-02-29"), None);
+    }
+
+    #[test]
+    fn from_unix_days_before_epoch() {
+        assert_eq!(
+            Date::from_unix_days(-1),
+            Date {
+                year: 1969,
+                month: 12,
+                day: 31
+            }
+        );
+    }
+}
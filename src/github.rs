@@ -1,32 +1,605 @@
-use std::collections::HashMap;
-use std::time::{Duration, UNIX_EPOCH};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant, UNIX_EPOCH};
 
-use anyhow::anyhow;
-use log::{debug, warn};
+use log::{debug, info, warn};
 use reqwest::header::{
-    HeaderMap, HeaderName, HeaderValue, ACCEPT, AUTHORIZATION, LINK, USER_AGENT,
+    HeaderMap, HeaderName, HeaderValue, ACCEPT, ETAG, IF_NONE_MATCH, LINK, USER_AGENT,
 };
-use reqwest::Client;
+use reqwest::{Certificate, Client};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use tokio::time::sleep;
 use url::Url;
 
+use crate::date::Date;
+use crate::github_app::GithubApp;
+use crate::health;
+use crate::metrics;
+use crate::rate_limit;
+use crate::token_pool;
+
 const MAX_RETRIES: u32 = 2;
-const INITIAL_BACKOFF: u64 = 2;
+/// Longest wait for an exhausted primary rate limit to reset; later resets fail the
+/// request with [`GithubError::RateLimited`] rather than hold it open.
+const MAX_RATE_LIMIT_WAIT_SECS: u64 = 5;
+/// GitHub usually finishes computing repository statistics within a minute.
+const STATS_PENDING_RETRY_SECS: u64 = 60;
+/// Delay before the first retry; doubled after each attempt. Shortened in tests so retry
+/// paths can run against the mock server without waiting.
+#[cfg(not(test))]
+const INITIAL_BACKOFF: Duration = Duration::from_secs(2);
+#[cfg(test)]
+const INITIAL_BACKOFF: Duration = Duration::from_millis(10);
 const REQUEST_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_API_URL: &str = "https://api.github.com";
 
-#[derive(Deserialize, Serialize, Debug)]
+/// Why a request to the GitHub API failed.
+#[derive(Debug)]
+pub enum GithubError {
+    /// GitHub refused the configured token or app credentials.
+    BadCredentials(String),
+    /// Rate limited, or the remaining budget cannot cover a crawl. `until` is the Unix
+    /// timestamp at which the limit resets, when known.
+    RateLimited {
+        until: Option<u64>,
+    },
+    UserNotFound(String),
+    /// Connection failures and timeouts.
+    Network(String),
+    /// GitHub kept answering with a server error.
+    Upstream(reqwest::StatusCode),
+    /// A response did not have the expected shape.
+    Decode(String),
+    /// The search API timed out and returned only part of this listing.
+    Incomplete(String),
+    /// GitHub was still computing contributor statistics for these repositories.
+    StatsPending(Vec<String>),
+    /// Any other unexpected status code.
+    Status(reqwest::StatusCode),
+    Other(String),
+}
+
+impl GithubError {
+    /// Short, actionable message for error cards and badges.
+    pub fn user_message(&self) -> String {
+        match self {
+            GithubError::BadCredentials(_) => {
+                String::from("GitHub rejected the configured credentials")
+            }
+            GithubError::RateLimited { until: Some(until) } => {
+                let secs = until % 86_400;
+                format!(
+                    "GitHub rate limit reached, retry after {:02}:{:02} UTC",
+                    secs / 3600,
+                    secs % 3600 / 60
+                )
+            }
+            GithubError::RateLimited { until: None } => String::from("GitHub rate limit reached"),
+            GithubError::UserNotFound(user) => format!("GitHub user '{user}' not found"),
+            GithubError::Network(_) => String::from("Could not reach GitHub"),
+            GithubError::Upstream(_) => String::from("GitHub is currently unavailable"),
+            GithubError::Decode(_) => String::from("Unexpected response from GitHub"),
+            GithubError::Incomplete(_) => {
+                String::from("GitHub returned incomplete results, retry later")
+            }
+            GithubError::StatsPending(_) => {
+                String::from("GitHub is still computing statistics, retry in a minute")
+            }
+            GithubError::Status(_) | GithubError::Other(_) => {
+                String::from("Failed to fetch GitHub stats")
+            }
+        }
+    }
+
+    /// A few words for space-constrained places such as badges.
+    pub fn short_message(&self) -> &'static str {
+        match self {
+            GithubError::RateLimited { .. } => "rate limited",
+            GithubError::UserNotFound(_) => "user not found",
+            GithubError::BadCredentials(_) => "bad credentials",
+            _ => "unavailable",
+        }
+    }
+
+    /// Seconds until retrying can succeed, for rate-limit errors and pending statistics.
+    pub fn retry_after(&self, now: u64) -> Option<u64> {
+        match self {
+            GithubError::RateLimited { until: Some(until) } => Some(until.saturating_sub(now)),
+            GithubError::StatsPending(_) => Some(STATS_PENDING_RETRY_SECS),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GithubError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GithubError::BadCredentials(detail) => write!(f, "Bad credentials: {detail}"),
+            GithubError::RateLimited { until: Some(until) } => {
+                write!(f, "Rate limited until {until}")
+            }
+            GithubError::RateLimited { until: None } => write!(f, "Rate limited"),
+            GithubError::UserNotFound(user) => write!(f, "GitHub user '{user}' not found"),
+            GithubError::Network(detail) => write!(f, "Failed to send request: {detail}"),
+            GithubError::Upstream(status) => {
+                write!(
+                    f,
+                    "Max retries of {MAX_RETRIES} reached, last status: {status}"
+                )
+            }
+            GithubError::Decode(detail) => write!(f, "Failed to decode response: {detail}"),
+            GithubError::Incomplete(listing) => {
+                write!(f, "GitHub search returned incomplete results for {listing}")
+            }
+            GithubError::StatsPending(repos) => write!(
+                f,
+                "Contributor statistics still being computed for {}",
+                repos.join(", ")
+            ),
+            GithubError::Status(status) => write!(f, "Request failed with status: {status}"),
+            GithubError::Other(detail) => f.write_str(detail),
+        }
+    }
+}
+
+impl std::error::Error for GithubError {}
+
+impl From<serde_json::Error> for GithubError {
+    fn from(err: serde_json::Error) -> Self {
+        GithubError::Decode(err.to_string())
+    }
+}
+
+impl From<reqwest::Error> for GithubError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_decode() {
+            GithubError::Decode(err.to_string())
+        } else if err.is_builder() {
+            GithubError::Other(err.to_string())
+        } else {
+            GithubError::Network(err.to_string())
+        }
+    }
+}
+
+impl From<url::ParseError> for GithubError {
+    fn from(err: url::ParseError) -> Self {
+        GithubError::Other(format!("Invalid URL: {err}"))
+    }
+}
+
+impl From<reqwest::header::InvalidHeaderValue> for GithubError {
+    fn from(err: reqwest::header::InvalidHeaderValue) -> Self {
+        GithubError::Other(format!("Invalid header value: {err}"))
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Stats {
     pub total_stars: u32,
     pub total_commits: u32,
     pub languages: HashMap<String, Language>,
+    /// Year `total_commits` is restricted to, if any.
+    #[serde(default)]
+    pub commits_year: Option<u16>,
+    /// Whether private repositories were part of the crawl.
+    #[serde(default)]
+    pub includes_private: bool,
+    /// Per-repository breakdown keyed by `full_name`. Also used to skip unchanged
+    /// repositories on the next refresh.
+    #[serde(default)]
+    pub repositories: HashMap<String, RepositoryStats>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct RepositoryStats {
+    pub stars: u32,
+    pub forks: u32,
+    pub fork: bool,
+    pub archived: bool,
+    pub private: bool,
+    /// Excluded from language totals by a [`RepoFilter`] rule; languages are not fetched.
+    pub ignored: bool,
+    /// Rules that matched this repository.
+    pub ignored_by: Vec<IgnoreRule>,
+    /// Stars left out of `total_stars` because a matching rule affects stars.
+    pub ignored_stars: bool,
+    /// `None` for repositories that have never been pushed to.
+    pub pushed_at: Option<String>,
+    /// Language name to bytes of code.
+    pub languages: HashMap<String, f64>,
 }
 
 #[derive(Deserialize, Debug)]
 struct Repository {
     full_name: String,
     stargazers_count: u32,
+    forks_count: u32,
+    fork: bool,
+    archived: bool,
+    #[serde(default)]
+    private: bool,
     languages_url: String,
+    pushed_at: Option<String>,
+    #[serde(default)]
+    topics: Vec<String>,
+    #[serde(default)]
+    is_template: bool,
+}
+
+#[derive(Deserialize)]
+struct AuthenticatedUser {
+    login: String,
+}
+
+/// How `total_commits` is computed.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitCountStrategy {
+    /// `search/commits?q=author:`. Cheap, but capped and misses unverified emails.
+    #[default]
+    Search,
+    /// Sums `contributionsCollection.totalCommitContributions` per year via GraphQL.
+    Graphql,
+    /// Sums the user's commits from each crawled repository's contributor statistics.
+    Contributors,
+}
+
+/// Location of the GitHub API: `api.github.com`, a GitHub Enterprise Server instance or a mock.
+#[derive(Debug, Clone)]
+pub struct GithubApi {
+    /// REST API root, e.g. `https://ghe.example.com/api/v3` for GitHub Enterprise Server.
+    pub api_url: String,
+    pub graphql_url: String,
+    /// PEM file with CA certificates trusted in addition to the built-in roots.
+    pub ca_certificate: Option<PathBuf>,
+}
+
+impl GithubApi {
+    /// Creates the API location, deriving the GraphQL endpoint from `api_url` when not given.
+    ///
+    /// GitHub Enterprise Server serves REST under `/api/v3` and GraphQL under `/api/graphql`,
+    /// while `api.github.com` serves GraphQL under `/graphql`.
+    pub fn new(api_url: &str, graphql_url: Option<&str>, ca_certificate: Option<PathBuf>) -> Self {
+        let api_url = api_url.trim_end_matches('/').to_string();
+        let graphql_url = match graphql_url {
+            Some(url) => url.to_string(),
+            None => match api_url.strip_suffix("/api/v3") {
+                Some(root) => format!("{root}/api/graphql"),
+                None => format!("{api_url}/graphql"),
+            },
+        };
+        GithubApi {
+            api_url,
+            graphql_url,
+            ca_certificate,
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.api_url)
+    }
+}
+
+impl Default for GithubApi {
+    fn default() -> Self {
+        GithubApi::new(DEFAULT_API_URL, None, None)
+    }
+}
+
+/// How requests to the GitHub API are authorized.
+#[derive(Debug, Clone)]
+pub enum Credentials {
+    /// Personal access tokens, rotated when one runs out of rate-limit quota.
+    Tokens(Vec<String>),
+    /// Short-lived installation tokens of a GitHub App, refreshed before they expire.
+    App(GithubApp),
+}
+
+impl Credentials {
+    /// Keys under which each credential's rate-limit budget and validity are tracked.
+    pub fn keys(&self) -> Vec<String> {
+        match self {
+            Credentials::Tokens(tokens) => tokens.iter().map(|t| token_pool::key(t)).collect(),
+            Credentials::App(app) => vec![format!("app {}", app.app_id)],
+        }
+    }
+
+    /// Labels for each credential, in the order of [`Credentials::keys`], safe to show in
+    /// logs and `/health`. Several tokens may share a label.
+    pub fn labels(&self) -> Vec<String> {
+        match self {
+            Credentials::Tokens(tokens) => tokens.iter().map(|t| token_pool::mask(t)).collect(),
+            Credentials::App(app) => vec![format!("app {}", app.app_id)],
+        }
+    }
+}
+
+/// HTTP client that authorizes every request with the current [`Credentials`].
+struct GithubClient<'a> {
+    http: Client,
+    api: &'a GithubApi,
+    github_user: &'a str,
+    credentials: &'a Credentials,
+}
+
+impl GithubClient<'_> {
+    /// Token for the next request. Resolved per request so that an installation token
+    /// expiring during a long crawl (or a rate-limit wait) is replaced in time, and so that
+    /// a pooled token with an exhausted `resource` budget is skipped.
+    async fn token(&self, resource: &str) -> Result<String, GithubError> {
+        match self.credentials {
+            Credentials::Tokens(tokens) => token_pool::select(tokens, resource)
+                .map(str::to_string)
+                .ok_or_else(|| GithubError::Other(String::from("No GitHub token configured"))),
+            Credentials::App(app) => {
+                app.installation_token(&self.http, self.api, self.github_user)
+                    .await
+            }
+        }
+    }
+
+    /// Key under which the rate-limit budget and validity of `token` are tracked.
+    fn credential_key(&self, token: &str) -> String {
+        match self.credentials {
+            Credentials::Tokens(_) => token_pool::key(token),
+            Credentials::App(app) => format!("app {}", app.app_id),
+        }
+    }
+
+    /// Label `token` is shown under.
+    fn credential_label(&self, token: &str) -> String {
+        match self.credentials {
+            Credentials::Tokens(_) => token_pool::mask(token),
+            Credentials::App(app) => format!("app {}", app.app_id),
+        }
+    }
+
+    /// The rate-limit resource a request to `url` is billed against.
+    fn resource_for(&self, url: &str) -> &'static str {
+        if url == self.api.graphql_url {
+            "graphql"
+        } else if url.starts_with(&self.api.url("/search/")) {
+            "search"
+        } else {
+            "core"
+        }
+    }
+
+    /// Records the budget reported for `token`. Returns true when another pooled token
+    /// still has budget left for `resource`.
+    fn record_budget(&self, token: &str, resource: &str, headers: &HeaderMap) -> bool {
+        let resource = header_string(headers, HeaderName::from_static("x-ratelimit-resource"))
+            .unwrap_or_else(|| resource.to_string());
+        rate_limit::record(
+            &self.credential_key(token),
+            &self.credential_label(token),
+            &resource,
+            rate_limit::Budget {
+                remaining: ratelimit_remaining(headers),
+                limit: ratelimit_limit(headers),
+                used: ratelimit_used(headers),
+                reset: ratelimit_reset_at(headers),
+            },
+        );
+        match self.credentials {
+            Credentials::Tokens(tokens) => {
+                token_pool::available(tokens, &resource).is_some_and(|t| t != token)
+            }
+            Credentials::App(_) => false,
+        }
+    }
+
+    /// Refuses to start work that needs `needed` requests against `resource` when the
+    /// known remaining budget cannot cover it.
+    fn ensure_budget(&self, resource: &str, needed: u64) -> Result<(), GithubError> {
+        let keys = self.credentials.keys();
+        match rate_limit::remaining(&keys, resource) {
+            Some(remaining) if remaining < needed => {
+                warn!("Rate-limit budget too low: about {needed} {resource} requests needed, {remaining} remaining");
+                Err(GithubError::RateLimited {
+                    until: rate_limit::next_reset(&keys, resource),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Settings controlling which repositories a crawl includes and how commits are counted.
+pub struct CrawlOptions<'a> {
+    pub filter: &'a RepoFilter,
+    pub exclude_forks: bool,
+    pub include_private: bool,
+    pub include_archived: bool,
+    pub include_org_repos: bool,
+    pub commit_count: CommitCountStrategy,
+    /// Restricts the commit count to a single calendar year.
+    pub commits_year: Option<u16>,
+}
+
+impl CrawlOptions<'_> {
+    /// The search API only sees public repositories owned by the user; anything beyond
+    /// that requires the authenticated `/user/repos` listing.
+    fn uses_authenticated_listing(&self) -> bool {
+        self.include_private || self.include_org_repos
+    }
+
+    /// Rate-limit resource and number of requests needed to list `repositories` repositories.
+    fn listing_requests(&self, repositories: usize) -> (&'static str, u64) {
+        let pages = repositories.div_ceil(100).max(1) as u64;
+        if self.uses_authenticated_listing() {
+            // Plus the `/user` lookup.
+            ("core", pages + 1)
+        } else {
+            ("search", pages)
+        }
+    }
+
+    /// Rate-limit resource and approximate number of requests the commit count needs.
+    fn commit_requests(&self, repositories: usize) -> (&'static str, u64) {
+        match self.commit_count {
+            CommitCountStrategy::Search => ("search", 1),
+            // Lifetime totals need the creation date plus at least the current year.
+            CommitCountStrategy::Graphql => {
+                ("graphql", if self.commits_year.is_some() { 1 } else { 2 })
+            }
+            CommitCountStrategy::Contributors => ("core", repositories as u64),
+        }
+    }
+
+    fn search_url(&self, api: &GithubApi, github_user: &str) -> String {
+        let fork_filter = if self.exclude_forks {
+            "+fork:false"
+        } else {
+            ""
+        };
+        let archived_filter = if self.include_archived {
+            ""
+        } else {
+            "+archived:false"
+        };
+        api.url(&format!(
+            "/search/repositories?q=user:{github_user}{fork_filter}{archived_filter}&per_page=100"
+        ))
+    }
+
+    fn user_repos_url(&self, api: &GithubApi) -> String {
+        let affiliation = if self.include_org_repos {
+            "owner,organization_member"
+        } else {
+            "owner"
+        };
+        let visibility = if self.include_private {
+            "all"
+        } else {
+            "public"
+        };
+        api.url(&format!(
+            "/user/repos?affiliation={affiliation}&visibility={visibility}&per_page=100"
+        ))
+    }
+
+    /// Applies the fork and archive filters the `/user/repos` listing cannot express.
+    fn keeps(&self, repo: &Repository) -> bool {
+        !(self.exclude_forks && repo.fork) && (self.include_archived || !repo.archived)
+    }
+}
+
+/// A kind of [`RepoFilter`] rule.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IgnoreRule {
+    Repositories,
+    Topics,
+    Archived,
+    Templates,
+}
+
+impl FromStr for IgnoreRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "repositories" => Ok(IgnoreRule::Repositories),
+            "topics" => Ok(IgnoreRule::Topics),
+            "archived" => Ok(IgnoreRule::Archived),
+            "templates" => Ok(IgnoreRule::Templates),
+            _ => Err(format!(
+                "unknown ignore rule '{s}' (expected repositories, topics, archived or templates)"
+            )),
+        }
+    }
+}
+
+/// Rules for leaving repositories and languages out of the totals.
+///
+/// Ignored repositories are still listed and always drop out of language totals; whether
+/// they also drop out of `total_stars` is chosen per rule with `affects_stars`.
+#[derive(Debug, Clone, Default)]
+pub struct RepoFilter {
+    /// `owner/repo` glob patterns, matched case-insensitively. `*` matches any run of
+    /// characters and `?` a single character.
+    pub repositories: Vec<String>,
+    /// Repository topics, matched case-insensitively.
+    pub topics: Vec<String>,
+    pub archived: bool,
+    pub templates: bool,
+    /// Languages removed from the totals regardless of repository.
+    pub languages: Vec<String>,
+    /// Rules whose matches are also excluded from `total_stars`.
+    pub affects_stars: Vec<IgnoreRule>,
+}
+
+impl RepoFilter {
+    fn matching_rules(&self, repo: &Repository) -> Vec<IgnoreRule> {
+        let full_name = repo.full_name.to_lowercase();
+        let mut rules = Vec::new();
+        if self
+            .repositories
+            .iter()
+            .any(|p| glob_matches(&p.to_lowercase(), &full_name))
+        {
+            rules.push(IgnoreRule::Repositories);
+        }
+        if repo
+            .topics
+            .iter()
+            .any(|t| self.topics.iter().any(|i| i.eq_ignore_ascii_case(t)))
+        {
+            rules.push(IgnoreRule::Topics);
+        }
+        if self.archived && repo.archived {
+            rules.push(IgnoreRule::Archived);
+        }
+        if self.templates && repo.is_template {
+            rules.push(IgnoreRule::Templates);
+        }
+        rules
+    }
+
+    fn ignores_stars(&self, rules: &[IgnoreRule]) -> bool {
+        rules.iter().any(|r| self.affects_stars.contains(r))
+    }
+
+    fn keeps_language(&self, name: &str) -> bool {
+        !self.languages.iter().any(|l| l.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Matches `text` against a glob `pattern` supporting `*` and `?`.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` seen and the text index it is currently matched up to.
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -48,65 +621,298 @@ struct CommitCount {
     total_count: u32,
 }
 
+/// A GitHub API response body together with the pagination header needed to continue.
+struct GithubResponse {
+    status: reqwest::StatusCode,
+    link: Option<String>,
+    body: String,
+}
+
+impl GithubResponse {
+    fn json<T: DeserializeOwned>(&self) -> Result<T, GithubError> {
+        Ok(serde_json::from_str(&self.body)?)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct CachedResponse {
+    etag: String,
+    link: Option<String>,
+    body: String,
+}
+
+/// Per-URL store of GitHub responses keyed by their `ETag`.
+///
+/// Each request for a known URL is sent with `If-None-Match`; when GitHub answers
+/// `304 Not Modified` the stored body is replayed instead. Conditional requests that
+/// return 304 do not count against the primary rate limit, so unchanged repositories
+/// cost nothing on refresh.
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct ResponseCache {
+    entries: HashMap<String, CachedResponse>,
+    /// URLs requested since the cache was loaded; everything else is dropped on save.
+    #[serde(skip)]
+    touched: HashSet<String>,
+}
+
+impl ResponseCache {
+    /// Loads the cache from `path`, starting empty when the file is missing or unreadable.
+    pub fn load(path: &Path) -> Self {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|err| {
+                debug!("Discarding unreadable response cache: {err}");
+                ResponseCache::default()
+            }),
+            Err(_) => ResponseCache::default(),
+        }
+    }
+
+    /// Writes the entries used by the last crawl to `path`, pruning stale URLs.
+    pub fn save(&mut self, path: &Path) -> Result<(), anyhow::Error> {
+        let touched = &self.touched;
+        self.entries.retain(|url, _| touched.contains(url));
+        fs::write(path, serde_json::to_string(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Drops the stored response for `url`, so the next request for it is not conditional.
+    fn forget(&mut self, url: &str) {
+        self.entries.remove(url);
+    }
+}
+
 async fn make_github_request(
-    client: &Client,
+    client: &GithubClient<'_>,
+    cache: &mut ResponseCache,
+    url: &str,
+) -> Result<GithubResponse, GithubError> {
+    send_github_request(client, Some(cache), url, None).await
+}
+
+#[derive(Deserialize)]
+struct GraphqlResponse<T> {
+    data: Option<T>,
+    errors: Option<Vec<GraphqlError>>,
+}
+
+#[derive(Deserialize)]
+struct GraphqlError {
+    message: String,
+    /// Machine-readable category such as `NOT_FOUND` or `RATE_LIMITED`.
+    #[serde(rename = "type")]
+    kind: Option<String>,
+}
+
+async fn make_graphql_request<T: DeserializeOwned>(
+    client: &GithubClient<'_>,
+    api: &GithubApi,
+    query: &str,
+    variables: serde_json::Value,
+) -> Result<T, GithubError> {
+    let body = serde_json::json!({ "query": query, "variables": variables });
+    let response: GraphqlResponse<T> =
+        send_github_request(client, None, &api.graphql_url, Some(&body))
+            .await?
+            .json()?;
+    if let Some(errors) = response.errors.filter(|e| !e.is_empty()) {
+        // GraphQL reports these with a 200, so they are mapped here rather than from the
+        // status like their REST counterparts.
+        let kind = |k: &str| errors.iter().any(|e| e.kind.as_deref() == Some(k));
+        if kind("RATE_LIMITED") {
+            return Err(GithubError::RateLimited {
+                until: rate_limit::next_reset(&client.credentials.keys(), "graphql"),
+            });
+        }
+        if kind("NOT_FOUND") {
+            return Err(GithubError::UserNotFound(client.github_user.to_string()));
+        }
+        let messages: Vec<String> = errors.into_iter().map(|e| e.message).collect();
+        return Err(GithubError::Other(format!(
+            "GraphQL query failed: {}",
+            messages.join("; ")
+        )));
+    }
+    response
+        .data
+        .ok_or_else(|| GithubError::Decode(String::from("GraphQL response contained no data")))
+}
+
+/// Sends a GET request (or a POST when `body` is given) with retries.
+///
+/// Responses take part in `ETag` caching when a `cache` is given, which only GETs do;
+/// GraphQL POSTs are always sent fresh.
+async fn send_github_request(
+    client: &GithubClient<'_>,
+    mut cache: Option<&mut ResponseCache>,
     url: &str,
-) -> Result<reqwest::Response, anyhow::Error> {
+    body: Option<&serde_json::Value>,
+) -> Result<GithubResponse, GithubError> {
     let mut retries = 0;
+    let mut attempt: u32 = 0;
     let mut backoff = INITIAL_BACKOFF;
+    let method = if body.is_some() { "POST" } else { "GET" };
+    if let Some(cache) = cache.as_deref_mut() {
+        cache.touched.insert(url.to_string());
+    }
 
     loop {
-        let response = match client.get(url).send().await {
+        attempt += 1;
+        let resource = client.resource_for(url);
+        let token = client.token(resource).await?;
+        let mut request = match body {
+            Some(body) => client.http.post(url).json(body),
+            None => client.http.get(url),
+        }
+        .bearer_auth(&token);
+        if let Some(cached) = cache.as_deref().and_then(|c| c.entries.get(url)) {
+            request = request.header(IF_NONE_MATCH, &cached.etag);
+        }
+
+        let started = Instant::now();
+        let response = match request.send().await {
             Ok(resp) => resp,
             Err(err) => {
+                info!(
+                    target: "github_stats::github",
+                    method,
+                    url,
+                    attempt,
+                    duration_ms = started.elapsed().as_millis() as u64,
+                    error = err.to_string();
+                    "GitHub {method} {url} failed"
+                );
+                metrics::github_response(None);
                 // Retry transient network errors (connect / timeout); fail fast on others.
                 if retries < MAX_RETRIES && (err.is_connect() || err.is_timeout()) {
                     debug!("Transient network error (attempt {}): {}", retries + 1, err);
+                    metrics::github_retry("network");
                     retries += 1;
-                    sleep(Duration::from_secs(backoff)).await;
+                    sleep(backoff).await;
                     backoff *= 2;
                     continue;
                 }
-                return Err(anyhow!("Failed to send request: {err}"));
+                return Err(GithubError::Network(err.to_string()));
             }
         };
 
         let headers = response.headers().clone();
         let status = response.status();
+        info!(
+            target: "github_stats::github",
+            method,
+            url,
+            attempt,
+            status = status.as_u16(),
+            duration_ms = started.elapsed().as_millis() as u64;
+            "GitHub {method} {url} {}", status.as_u16()
+        );
+        metrics::github_response(Some(status));
+        let can_rotate = client.record_budget(&token, resource, &headers);
 
         // Secondary rate limit: GitHub sends a Retry-After header.
         if let Some(secs) = retry_after(&headers) {
             if retries < MAX_RETRIES {
                 debug!("Secondary rate limit, sleeping {}s", secs);
+                metrics::github_retry("secondary_rate_limit");
+                metrics::rate_limit_sleep(Duration::from_secs(secs));
                 sleep(Duration::from_secs(secs)).await;
                 retries += 1;
                 continue;
             }
-            return Err(anyhow!(
-                "Secondary rate limit persists after {MAX_RETRIES} retries"
-            ));
+            warn!("Secondary rate limit persists after {MAX_RETRIES} retries");
+            return Err(GithubError::RateLimited {
+                until: now_secs().map(|now| now + secs),
+            });
         }
 
         // Primary rate limit: 403 with x-ratelimit-remaining: 0.
         if status == reqwest::StatusCode::FORBIDDEN {
+            if ratelimit_remaining(&headers) == Some(0) && can_rotate {
+                info!(
+                    "Rate limit exhausted for token {}, switching to the next token",
+                    token_pool::mask(&token)
+                );
+                continue;
+            }
             if ratelimit_remaining(&headers) == Some(0) {
                 if let Some(reset_secs) = ratelimit_reset(&headers) {
-                    if retries < MAX_RETRIES {
-                        debug!(
-                            "Primary rate limit exhausted, sleeping {}s until reset",
-                            reset_secs
-                        );
+                    if retries < MAX_RETRIES && reset_secs <= MAX_RATE_LIMIT_WAIT_SECS {
+                        info!("Primary rate limit exhausted, sleeping {reset_secs}s until reset");
+                        metrics::github_retry("primary_rate_limit");
+                        metrics::rate_limit_sleep(Duration::from_secs(reset_secs));
                         sleep(Duration::from_secs(reset_secs)).await;
                         retries += 1;
                         continue;
                     }
                 }
             }
-            return Err(anyhow!("Request forbidden (403)"));
+            if ratelimit_remaining(&headers) == Some(0) {
+                return Err(GithubError::RateLimited {
+                    until: ratelimit_reset_at(&headers),
+                });
+            }
+            return Err(GithubError::BadCredentials(String::from(
+                "Request forbidden (403); the credentials lack access",
+            )));
+        }
+
+        if status == reqwest::StatusCode::UNAUTHORIZED {
+            health::record_credential(&client.credential_key(&token), false);
+            return Err(GithubError::BadCredentials(String::from(
+                "GitHub rejected the credentials (401)",
+            )));
+        }
+
+        if status.is_success() || status == reqwest::StatusCode::NOT_MODIFIED {
+            health::record_credential(&client.credential_key(&token), true);
+        }
+
+        if status == reqwest::StatusCode::NOT_MODIFIED {
+            if let Some(cached) = cache.as_deref().and_then(|c| c.entries.get(url)) {
+                debug!("Not modified, reusing cached response for {url}");
+                return Ok(GithubResponse {
+                    status: reqwest::StatusCode::OK,
+                    link: cached.link.clone(),
+                    body: cached.body.clone(),
+                });
+            }
+            return Err(GithubError::Other(String::from(
+                "Not modified (304) without a cached response",
+            )));
+        }
+
+        // Statistics endpoints answer 202 while GitHub computes the data in the background.
+        if status == reqwest::StatusCode::ACCEPTED && retries < MAX_RETRIES {
+            debug!("Request accepted but not ready, retrying in {backoff:?}");
+            metrics::github_retry("accepted");
+            retries += 1;
+            sleep(backoff).await;
+            backoff *= 2;
+            continue;
         }
 
         if status.is_success() {
-            return Ok(response);
+            let etag = header_string(&headers, ETAG);
+            let link = header_string(&headers, LINK);
+            let body = response.text().await?;
+            if let Some(cache) = cache.as_deref_mut() {
+                match etag.filter(|_| status == reqwest::StatusCode::OK) {
+                    Some(etag) => {
+                        cache.entries.insert(
+                            url.to_string(),
+                            CachedResponse {
+                                etag,
+                                link: link.clone(),
+                                body: body.clone(),
+                            },
+                        );
+                    }
+                    None => {
+                        cache.entries.remove(url);
+                    }
+                }
+            }
+            return Ok(GithubResponse { status, link, body });
         }
 
         let retry_codes = [
@@ -120,20 +926,30 @@ async fn make_github_request(
 
         if retry_codes.contains(&status) && retries < MAX_RETRIES {
             debug!("Request failed with status: {}. Retrying...", status);
+            metrics::github_retry("status");
+        } else if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(GithubError::RateLimited {
+                until: ratelimit_reset_at(&headers),
+            });
         } else if retry_codes.contains(&status) {
-            return Err(anyhow!(
-                "Max retries of {MAX_RETRIES} reached, last status: {status}"
-            ));
+            return Err(GithubError::Upstream(status));
         } else {
-            return Err(anyhow!("Request failed with status: {status}"));
+            return Err(GithubError::Status(status));
         }
 
         retries += 1;
-        sleep(Duration::from_secs(backoff)).await;
+        sleep(backoff).await;
         backoff *= 2;
     }
 }
 
+fn header_string(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
 fn ratelimit_remaining(headers: &HeaderMap) -> Option<u64> {
     headers
         .get("x-ratelimit-remaining")
@@ -141,16 +957,39 @@ fn ratelimit_remaining(headers: &HeaderMap) -> Option<u64> {
         .and_then(|s| s.parse().ok())
 }
 
-fn ratelimit_reset(headers: &HeaderMap) -> Option<u64> {
-    let reset_timestamp: u64 = headers
+fn ratelimit_limit(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get("x-ratelimit-limit")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.parse().ok())
+}
+
+fn ratelimit_used(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get("x-ratelimit-used")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.parse().ok())
+}
+
+/// Unix timestamp at which the rate limit resets.
+fn ratelimit_reset_at(headers: &HeaderMap) -> Option<u64> {
+    headers
         .get("x-ratelimit-reset")
         .and_then(|v| v.to_str().ok())
-        .and_then(|s| s.parse().ok())?;
-    let now = std::time::SystemTime::now()
+        .and_then(|s| s.parse().ok())
+}
+
+/// Seconds until the rate limit resets.
+fn ratelimit_reset(headers: &HeaderMap) -> Option<u64> {
+    let reset_timestamp = ratelimit_reset_at(headers)?;
+    Some(reset_timestamp.saturating_sub(now_secs()?))
+}
+
+fn now_secs() -> Option<u64> {
+    std::time::SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .ok()?
-        .as_secs();
-    reset_timestamp.checked_sub(now)
+        .ok()
+        .map(|d| d.as_secs())
 }
 
 fn retry_after(headers: &HeaderMap) -> Option<u64> {
@@ -161,46 +1000,114 @@ fn retry_after(headers: &HeaderMap) -> Option<u64> {
 }
 
 async fn fetch_all_pages<T: DeserializeOwned>(
-    client: &Client,
+    client: &GithubClient<'_>,
+    cache: &mut ResponseCache,
     initial_url: &str,
-) -> Result<SearchResult<T>, anyhow::Error> {
+) -> Result<SearchResult<T>, GithubError> {
     debug!("Fetching all pages for {initial_url}");
     let mut total_count = 0;
+    let mut incomplete_results = false;
     let mut all_items = Vec::new();
     let mut next_url = Some(Url::parse(initial_url)?);
 
     while let Some(url) = next_url.take() {
-        let response = make_github_request(client, url.as_str()).await?;
-        let headers = response.headers().clone();
-        let result: SearchResult<T> = response.json().await?;
+        let response = make_github_request(client, cache, url.as_str()).await?;
+        let result: SearchResult<T> = response.json()?;
 
         if result.incomplete_results {
             warn!(
                 "GitHub search API returned incomplete results for {}; using partial data",
                 url
             );
+            // A 304 would replay the partial page on every refresh; fetch it in full instead.
+            cache.forget(url.as_str());
+            incomplete_results = true;
         }
 
         total_count = total_count.max(result.total_count);
         all_items.extend(result.items);
-        next_url = parse_next_url(&headers)?;
+        next_url = parse_next_url(response.link.as_deref());
     }
 
     Ok(SearchResult {
         total_count,
-        incomplete_results: false,
+        incomplete_results,
         items: all_items,
     })
 }
 
-fn parse_next_url(headers: &HeaderMap) -> Result<Option<Url>, anyhow::Error> {
-    if let Some(link_header) = headers.get(LINK) {
-        let links = parse_link_header(link_header.to_str()?);
-        if let Some(next) = links.get("next") {
-            return Ok(Some(next.clone()));
+/// Follows `Link: rel="next"` pagination for endpoints that return a plain JSON array.
+async fn fetch_all_list_pages<T: DeserializeOwned>(
+    client: &GithubClient<'_>,
+    cache: &mut ResponseCache,
+    initial_url: &str,
+) -> Result<Vec<T>, GithubError> {
+    debug!("Fetching all pages for {initial_url}");
+    let mut all_items = Vec::new();
+    let mut next_url = Some(Url::parse(initial_url)?);
+
+    while let Some(url) = next_url.take() {
+        let response = make_github_request(client, cache, url.as_str()).await?;
+        let items: Vec<T> = response.json()?;
+        all_items.extend(items);
+        next_url = parse_next_url(response.link.as_deref());
+    }
+
+    Ok(all_items)
+}
+
+/// Lists the repositories a crawl covers according to `options`.
+async fn list_repositories(
+    client: &GithubClient<'_>,
+    cache: &mut ResponseCache,
+    api: &GithubApi,
+    github_user: &str,
+    options: &CrawlOptions<'_>,
+) -> Result<Vec<Repository>, GithubError> {
+    if !options.uses_authenticated_listing() {
+        // The search API answers 422 when the `user:` qualifier names no existing account.
+        let result: SearchResult<Repository> =
+            fetch_all_pages(client, cache, &options.search_url(api, github_user))
+                .await
+                .map_err(|err| match err {
+                    GithubError::Status(reqwest::StatusCode::UNPROCESSABLE_ENTITY) => {
+                        GithubError::UserNotFound(github_user.to_string())
+                    }
+                    err => err,
+                })?;
+        // Partial listings would undercount stars and languages until the cache expires.
+        if result.incomplete_results {
+            return Err(GithubError::Incomplete(format!(
+                "the repositories of {github_user}"
+            )));
         }
+        return Ok(result.items);
     }
-    Ok(None)
+
+    if matches!(client.credentials, Credentials::App(_)) {
+        return Err(GithubError::Other(String::from(
+            "Private and organization repositories can only be listed with a personal access token, not as a GitHub App",
+        )));
+    }
+
+    // `/user/repos` lists the token owner's repositories, not those of `github_user`.
+    let user: AuthenticatedUser = make_github_request(client, cache, &api.url("/user"))
+        .await?
+        .json()?;
+    if !user.login.eq_ignore_ascii_case(github_user) {
+        return Err(GithubError::Other(format!(
+            "Token belongs to '{}', but private and organization repositories can only be listed for the token owner ('{github_user}' configured)",
+            user.login
+        )));
+    }
+
+    let repos: Vec<Repository> =
+        fetch_all_list_pages(client, cache, &options.user_repos_url(api)).await?;
+    Ok(repos.into_iter().filter(|r| options.keeps(r)).collect())
+}
+
+fn parse_next_url(link_header: Option<&str>) -> Option<Url> {
+    link_header.and_then(|header| parse_link_header(header).remove("next"))
 }
 
 fn parse_link_header(header: &str) -> HashMap<String, Url> {
@@ -240,98 +1147,474 @@ fn parse_link_header(header: &str) -> HashMap<String, Url> {
     links
 }
 
+fn build_client<'a>(
+    api: &'a GithubApi,
+    github_user: &'a str,
+    credentials: &'a Credentials,
+) -> Result<GithubClient<'a>, GithubError> {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        ACCEPT,
+        HeaderValue::from_static("application/vnd.github+json"),
+    );
+    headers.insert(
+        HeaderName::from_static("x-github-api-version"),
+        HeaderValue::from_static("2022-11-28"),
+    );
+    headers.insert(USER_AGENT, HeaderValue::from_str(github_user)?);
+
+    let mut builder = Client::builder()
+        .default_headers(headers)
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS));
+    if let Some(path) = &api.ca_certificate {
+        let pem = fs::read(path).map_err(|err| {
+            GithubError::Other(format!(
+                "Failed to read CA certificate {}: {err}",
+                path.display()
+            ))
+        })?;
+        builder = builder.tls_certs_merge(Certificate::from_pem_bundle(&pem)?);
+    }
+    Ok(GithubClient {
+        http: builder.build()?,
+        api,
+        github_user,
+        credentials,
+    })
+}
+
 impl Stats {
     pub async fn request(
+        api: &GithubApi,
         github_user: &str,
-        github_token: &str,
-        ignored_repos: &str,
-        exclude_forks: bool,
-    ) -> Result<Self, anyhow::Error> {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            ACCEPT,
-            HeaderValue::from_static("application/vnd.github+json"),
-        );
-        headers.insert(
-            AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer {github_token}"))?,
-        );
-        headers.insert(
-            HeaderName::from_static("x-github-api-version"),
-            HeaderValue::from_static("2022-11-28"),
-        );
-        headers.insert(USER_AGENT, HeaderValue::from_str(github_user)?);
+        credentials: &Credentials,
+        options: &CrawlOptions<'_>,
+        previous: Option<&Stats>,
+        cache: &mut ResponseCache,
+    ) -> Result<Self, GithubError> {
+        let client = build_client(api, github_user, credentials)?;
 
-        let client = Client::builder()
-            .default_headers(headers)
-            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
-            .build()?;
+        let known_repositories = previous.map_or(0, |p| p.repositories.len());
+        let (resource, needed) = options.listing_requests(known_repositories);
+        client.ensure_budget(resource, needed)?;
 
-        let fork_filter = if exclude_forks { "+fork:false" } else { "" };
-        let repo_result: SearchResult<Repository> = fetch_all_pages(
-            &client,
-            &format!(
-                "https://api.github.com/search/repositories?q=user:{github_user}{fork_filter}&per_page=100"
-            ),
-        )
-        .await?;
+        let repos = list_repositories(&client, cache, api, github_user, options).await?;
 
-        let total_stars = repo_result.items.iter().map(|r| r.stargazers_count).sum();
+        let language_requests = repos
+            .iter()
+            .filter(|r| {
+                options.filter.matching_rules(r).is_empty()
+                    && unchanged_snapshot(previous, r).is_none()
+            })
+            .count() as u64;
+        let (commit_resource, commit_requests) = options.commit_requests(repos.len());
+        if commit_resource == "core" {
+            client.ensure_budget("core", language_requests + commit_requests)?;
+        } else {
+            client.ensure_budget("core", language_requests)?;
+            client.ensure_budget(commit_resource, commit_requests)?;
+        }
 
-        let commit_count: CommitCount = make_github_request(
-            &client,
-            &format!("https://api.github.com/search/commits?q=author:{github_user}"),
-        )
-        .await?
-        .json()
-        .await?;
+        let total_commits = match options.commit_count {
+            CommitCountStrategy::Search => {
+                count_commits_search(&client, cache, api, github_user, options.commits_year).await?
+            }
+            CommitCountStrategy::Graphql => {
+                count_commits_graphql(&client, api, github_user, options.commits_year).await?
+            }
+            CommitCountStrategy::Contributors => {
+                count_commits_contributors(
+                    &client,
+                    cache,
+                    api,
+                    github_user,
+                    &repos,
+                    options.commits_year,
+                )
+                .await?
+            }
+        };
 
-        let ignored: Vec<String> = ignored_repos
-            .split(',')
-            .map(|s| s.trim().to_lowercase())
-            .filter(|s| !s.is_empty())
-            .collect();
+        let mut repositories: HashMap<String, RepositoryStats> = HashMap::new();
+        let mut reused = 0;
 
-        let mut languages: HashMap<String, Language> = HashMap::new();
-        let colors = crate::language_colors::colors();
+        for repo in &repos {
+            let ignored_by = options.filter.matching_rules(repo);
+            let mut snapshot = RepositoryStats {
+                stars: repo.stargazers_count,
+                forks: repo.forks_count,
+                fork: repo.fork,
+                archived: repo.archived,
+                private: repo.private,
+                ignored: !ignored_by.is_empty(),
+                ignored_stars: options.filter.ignores_stars(&ignored_by),
+                ignored_by,
+                pushed_at: repo.pushed_at.clone(),
+                languages: HashMap::new(),
+            };
 
-        for repo in repo_result
-            .items
-            .iter()
-            .filter(|r| !ignored.contains(&r.full_name.to_lowercase()))
-        {
-            let langs: HashMap<String, f64> = make_github_request(&client, &repo.languages_url)
-                .await?
-                .json()
-                .await?;
-
-            for (name, &size) in &langs {
-                let color = colors
-                    .get(name)
-                    .cloned()
-                    .unwrap_or_else(|| String::from("#FBFF00"));
-                languages
-                    .entry(name.clone())
-                    .and_modify(|e| e.size += size)
-                    .or_insert(Language {
-                        color,
-                        name: name.clone(),
-                        size,
-                    });
+            if !snapshot.ignored {
+                snapshot.languages = match unchanged_snapshot(previous, repo) {
+                    Some(previous) => {
+                        reused += 1;
+                        previous.languages.clone()
+                    }
+                    None => make_github_request(&client, cache, &repo.languages_url)
+                        .await?
+                        .json()?,
+                };
             }
+
+            repositories.insert(repo.full_name.clone(), snapshot);
         }
 
+        debug!(
+            "Reused languages for {reused} unchanged repositories, fetched {}",
+            repositories.values().filter(|r| !r.ignored).count() - reused
+        );
+
+        let total_stars = repositories
+            .values()
+            .filter(|r| !r.ignored_stars)
+            .map(|r| r.stars)
+            .sum();
+
         Ok(Stats {
             total_stars,
-            total_commits: commit_count.total_count,
-            languages,
+            total_commits,
+            commits_year: options.commits_year,
+            languages: aggregate_languages(&repositories, options.filter),
+            includes_private: repositories.values().any(|r| r.private),
+            repositories,
         })
     }
 }
 
+/// The previous snapshot of `repo` if its languages can be reused: it was not ignored and
+/// has not been pushed to since.
+fn unchanged_snapshot<'a>(
+    previous: Option<&'a Stats>,
+    repo: &Repository,
+) -> Option<&'a RepositoryStats> {
+    previous
+        .and_then(|p| p.repositories.get(&repo.full_name))
+        .filter(|p| !p.ignored && p.pushed_at.is_some() && p.pushed_at == repo.pushed_at)
+}
+
+fn search_commits_url(api: &GithubApi, github_user: &str, year: Option<u16>) -> String {
+    let date_filter = year
+        .map(|y| format!("+author-date:{y}-01-01..{y}-12-31"))
+        .unwrap_or_default();
+    api.url(&format!(
+        "/search/commits?q=author:{github_user}{date_filter}"
+    ))
+}
+
+async fn count_commits_search(
+    client: &GithubClient<'_>,
+    cache: &mut ResponseCache,
+    api: &GithubApi,
+    github_user: &str,
+    year: Option<u16>,
+) -> Result<u32, GithubError> {
+    let commit_count: CommitCount =
+        make_github_request(client, cache, &search_commits_url(api, github_user, year))
+            .await?
+            .json()?;
+    Ok(commit_count.total_count)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphqlUser<T> {
+    user: Option<T>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UserCreatedAt {
+    created_at: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UserContributions {
+    contributions_collection: ContributionsCollection,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContributionsCollection {
+    total_commit_contributions: u32,
+    total_pull_request_contributions: u32,
+    contribution_calendar: ContributionCalendar,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContributionCalendar {
+    total_contributions: u32,
+}
+
+const CREATED_AT_QUERY: &str = "query($login: String!) { user(login: $login) { createdAt } }";
+const CONTRIBUTIONS_QUERY: &str = "query($login: String!, $from: DateTime!, $to: DateTime!) { \
+    user(login: $login) { contributionsCollection(from: $from, to: $to) { \
+    totalCommitContributions totalPullRequestContributions contributionCalendar { totalContributions } } } }";
+
+/// Fetches the contributions between two dates (inclusive), which must lie within one year.
+async fn fetch_contributions(
+    client: &GithubClient<'_>,
+    api: &GithubApi,
+    github_user: &str,
+    from: Date,
+    to: Date,
+) -> Result<ContributionsCollection, GithubError> {
+    let contributions: GraphqlUser<UserContributions> = make_graphql_request(
+        client,
+        api,
+        CONTRIBUTIONS_QUERY,
+        serde_json::json!({
+            "login": github_user,
+            "from": format!("{from}T00:00:00Z"),
+            "to": format!("{to}T23:59:59Z"),
+        }),
+    )
+    .await?;
+    Ok(contributions
+        .user
+        .ok_or_else(|| GithubError::UserNotFound(github_user.to_string()))?
+        .contributions_collection)
+}
+
+/// Counts commit contributions via GraphQL.
+///
+/// `contributionsCollection` spans at most one year, so lifetime totals are summed
+/// year by year from the account's creation.
+async fn count_commits_graphql(
+    client: &GithubClient<'_>,
+    api: &GithubApi,
+    github_user: &str,
+    year: Option<u16>,
+) -> Result<u32, GithubError> {
+    let years = match year {
+        Some(year) => i32::from(year)..=i32::from(year),
+        None => {
+            let created: GraphqlUser<UserCreatedAt> = make_graphql_request(
+                client,
+                api,
+                CREATED_AT_QUERY,
+                serde_json::json!({ "login": github_user }),
+            )
+            .await?;
+            let created_at = created
+                .user
+                .ok_or_else(|| GithubError::UserNotFound(github_user.to_string()))?
+                .created_at;
+            let created_year: i32 = created_at
+                .get(..4)
+                .and_then(|y| y.parse().ok())
+                .ok_or_else(|| {
+                    GithubError::Decode(format!("Unexpected createdAt timestamp '{created_at}'"))
+                })?;
+            created_year..=Date::today().year
+        }
+    };
+
+    let mut total = 0;
+    for year in years {
+        total += fetch_contributions(
+            client,
+            api,
+            github_user,
+            Date::first_of_year(year),
+            Date::last_of_year(year),
+        )
+        .await?
+        .total_commit_contributions;
+    }
+    Ok(total)
+}
+
+/// An inclusive date range for time-windowed stats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    pub since: Date,
+    pub until: Date,
+}
+
+impl Window {
+    /// Splits the window at year boundaries; GraphQL contribution queries span at most a year.
+    fn year_chunks(&self) -> Vec<(Date, Date)> {
+        (self.since.year..=self.until.year)
+            .map(|year| {
+                (
+                    self.since.max(Date::first_of_year(year)),
+                    self.until.min(Date::last_of_year(year)),
+                )
+            })
+            .collect()
+    }
+
+    /// True when the window covers exactly one calendar year.
+    pub fn is_single_year(&self) -> bool {
+        self.since == Date::first_of_year(self.since.year)
+            && self.until == Date::last_of_year(self.since.year)
+    }
+}
+
+/// Contribution counts for a single time window.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WindowStats {
+    pub since: String,
+    pub until: String,
+    pub commits: u32,
+    pub pull_requests: u32,
+    pub contributions: u32,
+}
+
+impl WindowStats {
+    pub async fn request(
+        api: &GithubApi,
+        github_user: &str,
+        credentials: &Credentials,
+        window: &Window,
+    ) -> Result<Self, GithubError> {
+        let client = build_client(api, github_user, credentials)?;
+        client.ensure_budget("graphql", window.year_chunks().len() as u64)?;
+        let mut stats = WindowStats {
+            since: window.since.to_string(),
+            until: window.until.to_string(),
+            commits: 0,
+            pull_requests: 0,
+            contributions: 0,
+        };
+        for (from, to) in window.year_chunks() {
+            let collection = fetch_contributions(&client, api, github_user, from, to).await?;
+            stats.commits += collection.total_commit_contributions;
+            stats.pull_requests += collection.total_pull_request_contributions;
+            stats.contributions += collection.contribution_calendar.total_contributions;
+        }
+        Ok(stats)
+    }
+}
+
+#[derive(Deserialize)]
+struct ContributorStats {
+    author: Option<ContributorAuthor>,
+    total: u32,
+    weeks: Vec<ContributorWeek>,
+}
+
+#[derive(Deserialize)]
+struct ContributorAuthor {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct ContributorWeek {
+    /// Start of the week as a Unix timestamp.
+    w: i64,
+    /// Number of commits in the week.
+    c: u32,
+}
+
+/// Extracts `github_user`'s commit count from a repository's contributor statistics.
+fn contributor_commits(
+    contributors: &[ContributorStats],
+    github_user: &str,
+    year: Option<u16>,
+) -> u32 {
+    contributors
+        .iter()
+        .filter(|c| {
+            c.author
+                .as_ref()
+                .is_some_and(|a| a.login.eq_ignore_ascii_case(github_user))
+        })
+        .map(|c| match year {
+            Some(year) => c
+                .weeks
+                .iter()
+                .filter(|w| Date::from_unix_days(w.w.div_euclid(86_400)).year == i32::from(year))
+                .map(|w| w.c)
+                .sum(),
+            None => c.total,
+        })
+        .sum()
+}
+
+/// Counts the user's commits on the default branch of every crawled repository.
+async fn count_commits_contributors(
+    client: &GithubClient<'_>,
+    cache: &mut ResponseCache,
+    api: &GithubApi,
+    github_user: &str,
+    repos: &[Repository],
+    year: Option<u16>,
+) -> Result<u32, GithubError> {
+    let mut total = 0;
+    let mut pending = Vec::new();
+    for repo in repos {
+        let url = api.url(&format!("/repos/{}/stats/contributors", repo.full_name));
+        let response = make_github_request(client, cache, &url).await?;
+        match response.status {
+            reqwest::StatusCode::OK => {
+                let contributors: Vec<ContributorStats> = response.json()?;
+                total += contributor_commits(&contributors, github_user, year);
+            }
+            // Still being computed after retries. The remaining repositories are requested
+            // anyway, so GitHub computes theirs in the meantime too.
+            reqwest::StatusCode::ACCEPTED => pending.push(repo.full_name.clone()),
+            // 204: empty repository.
+            status => debug!(
+                "No contributor statistics for {} ({status}); skipping",
+                repo.full_name
+            ),
+        }
+    }
+    // A total missing some repositories would be cached as if it were complete.
+    if !pending.is_empty() {
+        return Err(GithubError::StatsPending(pending));
+    }
+    Ok(total)
+}
+
+/// Sums per-repository language byte counts into the totals shown on cards.
+fn aggregate_languages(
+    repositories: &HashMap<String, RepositoryStats>,
+    filter: &RepoFilter,
+) -> HashMap<String, Language> {
+    let mut languages: HashMap<String, Language> = HashMap::new();
+    let colors = crate::language_colors::colors();
+
+    for (name, &size) in repositories
+        .values()
+        .filter(|r| !r.ignored)
+        .flat_map(|r| &r.languages)
+        .filter(|(name, _)| filter.keeps_language(name))
+    {
+        let color = colors
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| String::from("#FBFF00"));
+        languages
+            .entry(name.clone())
+            .and_modify(|e| e.size += size)
+            .or_insert(Language {
+                color,
+                name: name.clone(),
+                size,
+            });
+    }
+
+    languages
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock_github::{MockGithub, MockResponse};
+    use crate::test_dir::TestDir;
 
     #[test]
     fn parse_link_header_basic() {
@@ -362,6 +1645,438 @@ mod tests {
         assert!(parse_link_header("").is_empty());
     }
 
+    #[test]
+    fn parse_next_url_follows_next_relation() {
+        let header = r#"<https://api.github.com/search/repositories?page=2>; rel="next", <https://api.github.com/search/repositories?page=5>; rel="last""#;
+        let next = parse_next_url(Some(header)).unwrap();
+        assert_eq!(next.query(), Some("page=2"));
+    }
+
+    #[test]
+    fn parse_next_url_last_page() {
+        let header = r#"<https://api.github.com/search/repositories?page=1>; rel="prev""#;
+        assert!(parse_next_url(Some(header)).is_none());
+        assert!(parse_next_url(None).is_none());
+    }
+
+    #[test]
+    fn response_cache_save_prunes_untouched_urls() {
+        let dir = TestDir::new("response-cache-prune");
+        let path = dir.path().join("cache.json");
+
+        let mut cache = ResponseCache::default();
+        for url in ["https://a", "https://b"] {
+            cache.entries.insert(
+                url.to_string(),
+                CachedResponse {
+                    etag: format!("\"{url}\""),
+                    link: None,
+                    body: "{}".to_string(),
+                },
+            );
+        }
+        cache.touched.insert("https://a".to_string());
+        cache.save(&path).unwrap();
+
+        let loaded = ResponseCache::load(&path);
+        assert!(loaded.entries.contains_key("https://a"));
+        assert!(!loaded.entries.contains_key("https://b"));
+        assert!(loaded.touched.is_empty());
+    }
+
+    #[test]
+    fn response_cache_load_missing_file_is_empty() {
+        let cache = ResponseCache::load(Path::new("/nonexistent/github-stats/cache.json"));
+        assert!(cache.entries.is_empty());
+    }
+
+    fn make_repo(pushed_at: &str, languages: &[(&str, f64)]) -> RepositoryStats {
+        RepositoryStats {
+            pushed_at: Some(pushed_at.to_string()),
+            languages: languages
+                .iter()
+                .map(|(name, size)| (name.to_string(), *size))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn aggregate_languages_sums_across_repositories() {
+        let mut repos = HashMap::new();
+        repos.insert(
+            "u/a".to_string(),
+            make_repo("2024-01-01T00:00:00Z", &[("Rust", 100.0), ("C", 10.0)]),
+        );
+        repos.insert(
+            "u/b".to_string(),
+            make_repo("2024-01-02T00:00:00Z", &[("Rust", 50.0)]),
+        );
+        let languages = aggregate_languages(&repos, &RepoFilter::default());
+        assert_eq!(languages.len(), 2);
+        assert_eq!(languages["Rust"].size, 150.0);
+        assert_eq!(languages["C"].size, 10.0);
+        assert_eq!(languages["Rust"].color, "#dea584");
+    }
+
+    #[test]
+    fn aggregate_languages_unknown_language_gets_fallback_color() {
+        let mut repos = HashMap::new();
+        repos.insert(
+            "u/a".to_string(),
+            make_repo("2024-01-01T00:00:00Z", &[("NotALanguage", 1.0)]),
+        );
+        assert_eq!(
+            aggregate_languages(&repos, &RepoFilter::default())["NotALanguage"].color,
+            "#FBFF00"
+        );
+    }
+
+    #[test]
+    fn aggregate_languages_skips_ignored_repositories() {
+        let mut repos = HashMap::new();
+        repos.insert(
+            "u/a".to_string(),
+            make_repo("2024-01-01T00:00:00Z", &[("Rust", 100.0)]),
+        );
+        let mut ignored = make_repo("2024-01-01T00:00:00Z", &[("C", 10.0)]);
+        ignored.ignored = true;
+        repos.insert("u/b".to_string(), ignored);
+        let languages = aggregate_languages(&repos, &RepoFilter::default());
+        assert!(languages.contains_key("Rust"));
+        assert!(!languages.contains_key("C"));
+    }
+
+    #[test]
+    fn repository_stats_missing_fields_default() {
+        let json = r#"{"pushed_at":"2024-01-01T00:00:00Z","languages":{"Rust":1.0}}"#;
+        let repo: RepositoryStats = serde_json::from_str(json).unwrap();
+        assert_eq!(repo.stars, 0);
+        assert!(!repo.ignored);
+        assert_eq!(repo.languages["Rust"], 1.0);
+    }
+
+    #[test]
+    fn stats_without_repositories_deserializes() {
+        let json = r#"{"total_stars":1,"total_commits":2,"languages":{}}"#;
+        let stats: Stats = serde_json::from_str(json).unwrap();
+        assert!(stats.repositories.is_empty());
+    }
+
+    fn make_options(include_private: bool, include_org_repos: bool) -> CrawlOptions<'static> {
+        static FILTER: RepoFilter = RepoFilter {
+            repositories: Vec::new(),
+            topics: Vec::new(),
+            archived: false,
+            templates: false,
+            languages: Vec::new(),
+            affects_stars: Vec::new(),
+        };
+        CrawlOptions {
+            filter: &FILTER,
+            exclude_forks: false,
+            include_private,
+            include_archived: true,
+            include_org_repos,
+            commit_count: CommitCountStrategy::Search,
+            commits_year: None,
+        }
+    }
+
+    fn make_listed_repo(fork: bool, archived: bool) -> Repository {
+        Repository {
+            full_name: "u/r".to_string(),
+            stargazers_count: 0,
+            forks_count: 0,
+            fork,
+            archived,
+            private: false,
+            languages_url: String::new(),
+            pushed_at: None,
+            topics: Vec::new(),
+            is_template: false,
+        }
+    }
+
+    #[test]
+    fn crawl_options_search_url_filters() {
+        let mut options = make_options(false, false);
+        assert!(!options.uses_authenticated_listing());
+        assert_eq!(
+            options.search_url(&GithubApi::default(), "octo"),
+            "https://api.github.com/search/repositories?q=user:octo&per_page=100"
+        );
+        options.exclude_forks = true;
+        options.include_archived = false;
+        assert!(options
+            .search_url(&GithubApi::default(), "octo")
+            .contains("q=user:octo+fork:false+archived:false&"));
+    }
+
+    #[test]
+    fn crawl_options_user_repos_url_affiliation() {
+        let options = make_options(true, false);
+        assert!(options.uses_authenticated_listing());
+        assert!(options
+            .user_repos_url(&GithubApi::default())
+            .contains("affiliation=owner&visibility=all"));
+
+        let options = make_options(false, true);
+        assert!(options
+            .user_repos_url(&GithubApi::default())
+            .contains("affiliation=owner,organization_member&visibility=public"));
+    }
+
+    #[test]
+    fn crawl_options_keeps_filters_forks_and_archived() {
+        let mut options = make_options(true, false);
+        assert!(options.keeps(&make_listed_repo(true, true)));
+        options.exclude_forks = true;
+        assert!(!options.keeps(&make_listed_repo(true, false)));
+        options.include_archived = false;
+        assert!(!options.keeps(&make_listed_repo(false, true)));
+        assert!(options.keeps(&make_listed_repo(false, false)));
+    }
+
+    #[test]
+    fn glob_matches_wildcards() {
+        assert!(glob_matches("octo/dotfiles*", "octo/dotfiles"));
+        assert!(glob_matches("octo/dotfiles*", "octo/dotfiles-old"));
+        assert!(glob_matches("*/scratch-?", "someone/scratch-1"));
+        assert!(glob_matches("octo/*-*-demo", "octo/a-b-c-demo"));
+        assert!(!glob_matches("octo/dotfiles*", "octo/my-dotfiles"));
+        assert!(!glob_matches("*/scratch-?", "someone/scratch-10"));
+        assert!(glob_matches("*", ""));
+        assert!(!glob_matches("", "octo/a"));
+    }
+
+    #[test]
+    fn repo_filter_matches_each_rule() {
+        let filter = RepoFilter {
+            repositories: vec!["U/Dot*".to_string()],
+            topics: vec!["Homework".to_string()],
+            archived: true,
+            templates: true,
+            ..Default::default()
+        };
+        let mut repo = make_listed_repo(false, false);
+        assert!(filter.matching_rules(&repo).is_empty());
+
+        repo.full_name = "u/dotfiles".to_string();
+        repo.topics = vec!["homework".to_string()];
+        repo.archived = true;
+        repo.is_template = true;
+        assert_eq!(
+            filter.matching_rules(&repo),
+            [
+                IgnoreRule::Repositories,
+                IgnoreRule::Topics,
+                IgnoreRule::Archived,
+                IgnoreRule::Templates
+            ]
+        );
+    }
+
+    #[test]
+    fn repo_filter_stars_only_for_chosen_rules() {
+        let filter = RepoFilter {
+            affects_stars: vec![IgnoreRule::Topics],
+            ..Default::default()
+        };
+        assert!(!filter.ignores_stars(&[]));
+        assert!(!filter.ignores_stars(&[IgnoreRule::Repositories]));
+        assert!(filter.ignores_stars(&[IgnoreRule::Repositories, IgnoreRule::Topics]));
+    }
+
+    #[test]
+    fn aggregate_languages_drops_excluded_languages() {
+        let mut repos = HashMap::new();
+        repos.insert(
+            "u/a".to_string(),
+            make_repo("2024-01-01T00:00:00Z", &[("Rust", 100.0), ("HTML", 500.0)]),
+        );
+        let filter = RepoFilter {
+            languages: vec!["html".to_string()],
+            ..Default::default()
+        };
+        let languages = aggregate_languages(&repos, &filter);
+        assert!(languages.contains_key("Rust"));
+        assert!(!languages.contains_key("HTML"));
+    }
+
+    #[test]
+    fn ignore_rule_from_str() {
+        assert_eq!("templates".parse(), Ok(IgnoreRule::Templates));
+        assert!("forks".parse::<IgnoreRule>().is_err());
+    }
+
+    #[test]
+    fn github_api_derives_graphql_url() {
+        let api = GithubApi::default();
+        assert_eq!(api.graphql_url, "https://api.github.com/graphql");
+        assert_eq!(api.url("/user"), "https://api.github.com/user");
+
+        let api = GithubApi::new("https://ghe.example.com/api/v3/", None, None);
+        assert_eq!(api.api_url, "https://ghe.example.com/api/v3");
+        assert_eq!(api.graphql_url, "https://ghe.example.com/api/graphql");
+
+        // Only GHE's `/api/v3` root is special; other paths ending in v3 are not.
+        let api = GithubApi::new("https://proxy.example.com/github/v3", None, None);
+        assert_eq!(
+            api.graphql_url,
+            "https://proxy.example.com/github/v3/graphql"
+        );
+
+        let api = GithubApi::new(
+            "http://127.0.0.1:9000",
+            Some("http://127.0.0.1:9001/gql"),
+            None,
+        );
+        assert_eq!(api.graphql_url, "http://127.0.0.1:9001/gql");
+    }
+
+    #[test]
+    fn github_api_urls_use_configured_root() {
+        let api = GithubApi::new("https://ghe.example.com/api/v3", None, None);
+        assert_eq!(
+            search_commits_url(&api, "octo", None),
+            "https://ghe.example.com/api/v3/search/commits?q=author:octo"
+        );
+        assert!(make_options(true, false)
+            .user_repos_url(&api)
+            .starts_with("https://ghe.example.com/api/v3/user/repos?"));
+    }
+
+    #[test]
+    fn crawl_options_estimate_requests() {
+        let mut options = make_options(false, false);
+        assert_eq!(options.listing_requests(0), ("search", 1));
+        assert_eq!(options.listing_requests(250), ("search", 3));
+        assert_eq!(options.commit_requests(250), ("search", 1));
+
+        options.include_private = true;
+        options.commit_count = CommitCountStrategy::Contributors;
+        assert_eq!(options.listing_requests(100), ("core", 2));
+        assert_eq!(options.commit_requests(250), ("core", 250));
+
+        options.commit_count = CommitCountStrategy::Graphql;
+        assert_eq!(options.commit_requests(250), ("graphql", 2));
+        options.commits_year = Some(2025);
+        assert_eq!(options.commit_requests(250), ("graphql", 1));
+    }
+
+    #[test]
+    fn github_client_resource_for_url() {
+        let api = GithubApi::default();
+        let credentials = Credentials::Tokens(vec!["token".to_string()]);
+        let client = build_client(&api, "octo", &credentials).unwrap();
+        assert_eq!(
+            client.resource_for("https://api.github.com/graphql"),
+            "graphql"
+        );
+        assert_eq!(
+            client.resource_for("https://api.github.com/search/commits?q=author:octo"),
+            "search"
+        );
+        assert_eq!(
+            client.resource_for("https://api.github.com/user/repos"),
+            "core"
+        );
+    }
+
+    #[test]
+    fn github_error_user_messages() {
+        // 2024-01-01T13:05:00Z
+        let until = 19_723 * 86_400 + 13 * 3600 + 5 * 60;
+        let err = GithubError::RateLimited { until: Some(until) };
+        assert_eq!(
+            err.user_message(),
+            "GitHub rate limit reached, retry after 13:05 UTC"
+        );
+        assert_eq!(err.retry_after(until - 30), Some(30));
+        assert_eq!(err.retry_after(until + 30), Some(0));
+        assert_eq!(
+            GithubError::UserNotFound("octo".to_string()).user_message(),
+            "GitHub user 'octo' not found"
+        );
+        assert_eq!(GithubError::Network(String::new()).retry_after(0), None);
+    }
+
+    #[test]
+    fn github_error_from_json_is_decode() {
+        let err: GithubError = serde_json::from_str::<Repository>("{}").unwrap_err().into();
+        assert!(matches!(err, GithubError::Decode(_)));
+    }
+
+    #[test]
+    fn search_commits_url_year_filter() {
+        assert_eq!(
+            search_commits_url(&GithubApi::default(), "octo", None),
+            "https://api.github.com/search/commits?q=author:octo"
+        );
+        assert_eq!(
+            search_commits_url(&GithubApi::default(), "octo", Some(2025)),
+            "https://api.github.com/search/commits?q=author:octo+author-date:2025-01-01..2025-12-31"
+        );
+    }
+
+    #[test]
+    fn commit_count_strategy_deserializes_lowercase() {
+        let strategy: CommitCountStrategy = serde_json::from_str(r#""contributors""#).unwrap();
+        assert_eq!(strategy, CommitCountStrategy::Contributors);
+        assert!(serde_json::from_str::<CommitCountStrategy>(r#""Graphql""#).is_err());
+    }
+
+    #[test]
+    fn contributor_commits_matches_user_and_year() {
+        let json = r#"[
+            {"author":{"login":"Octo"},"total":5,"weeks":[
+                {"w":1704067200,"c":2},
+                {"w":1735689600,"c":3}
+            ]},
+            {"author":{"login":"someone"},"total":100,"weeks":[]},
+            {"author":null,"total":7,"weeks":[]}
+        ]"#;
+        let contributors: Vec<ContributorStats> = serde_json::from_str(json).unwrap();
+        assert_eq!(contributor_commits(&contributors, "octo", None), 5);
+        // 1704067200 = 2024-01-01, 1735689600 = 2025-01-01
+        assert_eq!(contributor_commits(&contributors, "octo", Some(2024)), 2);
+        assert_eq!(contributor_commits(&contributors, "octo", Some(2025)), 3);
+        assert_eq!(contributor_commits(&contributors, "nobody", None), 0);
+    }
+
+    fn make_window(since: &str, until: &str) -> Window {
+        Window {
+            since: Date::parse(since).unwrap(),
+            until: Date::parse(until).unwrap(),
+        }
+    }
+
+    #[test]
+    fn window_year_chunks_single_year() {
+        let window = make_window("2025-03-01", "2025-06-30");
+        assert_eq!(window.year_chunks(), vec![(window.since, window.until)]);
+        assert!(!window.is_single_year());
+        assert!(make_window("2025-01-01", "2025-12-31").is_single_year());
+    }
+
+    #[test]
+    fn window_year_chunks_split_at_year_boundaries() {
+        let chunks: Vec<String> = make_window("2023-07-15", "2025-02-01")
+            .year_chunks()
+            .iter()
+            .map(|(from, to)| format!("{from}..{to}"))
+            .collect();
+        assert_eq!(
+            chunks,
+            [
+                "2023-07-15..2023-12-31",
+                "2024-01-01..2024-12-31",
+                "2025-01-01..2025-02-01"
+            ]
+        );
+    }
+
     #[test]
     fn ratelimit_remaining_parses() {
         let mut headers = HeaderMap::new();
@@ -392,4 +2107,381 @@ mod tests {
     fn retry_after_missing_returns_none() {
         assert_eq!(retry_after(&HeaderMap::new()), None);
     }
+
+    const SEARCH_REPOS: &str = "/search/repositories?q=user:octo&per_page=100";
+    const SEARCH_COMMITS: &str = "/search/commits?q=author:octo";
+
+    /// Token budgets are global state, so each test uses its own tokens.
+    fn tokens(tokens: &[&str]) -> Credentials {
+        Credentials::Tokens(tokens.iter().map(|t| t.to_string()).collect())
+    }
+
+    fn mock_repo(mock: &MockGithub, name: &str, stars: u32) -> serde_json::Value {
+        serde_json::json!({
+            "full_name": format!("octo/{name}"),
+            "stargazers_count": stars,
+            "forks_count": 0,
+            "fork": false,
+            "archived": false,
+            "languages_url": format!("{}/repos/octo/{name}/languages", mock.url()),
+            "pushed_at": "2024-01-01T00:00:00Z",
+        })
+    }
+
+    fn search_page(items: Vec<serde_json::Value>, incomplete: bool) -> MockResponse {
+        MockResponse::json(serde_json::json!({
+            "total_count": 2,
+            "incomplete_results": incomplete,
+            "items": items,
+        }))
+    }
+
+    #[actix_web::test]
+    async fn stats_request_follows_pagination_and_reuses_languages() {
+        let mock = MockGithub::start().await;
+        let page_2 = format!("{SEARCH_REPOS}&page=2");
+        mock.on(
+            SEARCH_REPOS,
+            [search_page(vec![mock_repo(&mock, "a", 3)], false)
+                .header("link", format!(r#"<{}{page_2}>; rel="next""#, mock.url()))],
+        );
+        mock.on(
+            &page_2,
+            [search_page(vec![mock_repo(&mock, "b", 4)], false)],
+        );
+        mock.on(
+            "/repos/octo/a/languages",
+            [MockResponse::json(serde_json::json!({"Rust": 100}))],
+        );
+        mock.on(
+            "/repos/octo/b/languages",
+            [MockResponse::json(serde_json::json!({"Rust": 50, "C": 10}))],
+        );
+        mock.on(
+            SEARCH_COMMITS,
+            [MockResponse::json(serde_json::json!({"total_count": 42}))],
+        );
+
+        let credentials = tokens(&["test-token-page"]);
+        let options = make_options(false, false);
+        let mut cache = ResponseCache::default();
+        let stats = Stats::request(
+            &mock.api(),
+            "octo",
+            &credentials,
+            &options,
+            None,
+            &mut cache,
+        )
+        .await
+        .unwrap();
+        assert_eq!(stats.total_stars, 7);
+        assert_eq!(stats.total_commits, 42);
+        assert_eq!(stats.repositories.len(), 2);
+        assert_eq!(stats.languages["Rust"].size, 150.0);
+        assert_eq!(mock.hits(&page_2), 1);
+        assert!(mock.requests().iter().all(|r| r.method == "GET"));
+        assert_eq!(
+            mock.requests()[0].headers["authorization"],
+            "Bearer test-token-page"
+        );
+
+        let again = Stats::request(
+            &mock.api(),
+            "octo",
+            &credentials,
+            &options,
+            Some(&stats),
+            &mut cache,
+        )
+        .await
+        .unwrap();
+        assert_eq!(again.languages["C"].size, 10.0);
+        assert_eq!(mock.hits("/repos/octo/b/languages"), 1);
+    }
+
+    #[actix_web::test]
+    async fn fetch_all_pages_reports_incomplete_results() {
+        let mock = MockGithub::start().await;
+        mock.on(
+            SEARCH_REPOS,
+            [search_page(vec![mock_repo(&mock, "a", 1)], true).header("etag", "\"partial\"")],
+        );
+        let api = mock.api();
+        let credentials = tokens(&["test-token-incomplete"]);
+        let client = build_client(&api, "octo", &credentials).unwrap();
+        let url = format!("{}{SEARCH_REPOS}", mock.url());
+        let mut cache = ResponseCache::default();
+        let result: SearchResult<Repository> =
+            fetch_all_pages(&client, &mut cache, &url).await.unwrap();
+        assert_eq!(result.items.len(), 1);
+        assert!(result.incomplete_results);
+        assert!(!cache.entries.contains_key(&url));
+    }
+
+    #[actix_web::test]
+    async fn pending_contributor_statistics_fail_the_count() {
+        let mock = MockGithub::start().await;
+        mock.on(
+            "/repos/octo/cold/stats/contributors",
+            [MockResponse::status(202)],
+        );
+        mock.on(
+            "/repos/octo/warm/stats/contributors",
+            [MockResponse::json(serde_json::json!([
+                {"author": {"login": "octo"}, "total": 4, "weeks": []}
+            ]))],
+        );
+        let repos: Vec<Repository> = ["cold", "warm"]
+            .iter()
+            .map(|name| serde_json::from_value(mock_repo(&mock, name, 0)).unwrap())
+            .collect();
+        let api = mock.api();
+        let credentials = tokens(&["test-token-contributors-202"]);
+        let client = build_client(&api, "octo", &credentials).unwrap();
+
+        let err = count_commits_contributors(
+            &client,
+            &mut ResponseCache::default(),
+            &api,
+            "octo",
+            &repos,
+            None,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(&err, GithubError::StatsPending(repos) if repos == &["octo/cold"]));
+        assert_eq!(err.retry_after(0), Some(STATS_PENDING_RETRY_SECS));
+        assert_eq!(
+            mock.hits("/repos/octo/cold/stats/contributors"),
+            MAX_RETRIES as usize + 1
+        );
+        assert_eq!(mock.hits("/repos/octo/warm/stats/contributors"), 1);
+    }
+
+    async fn count_commits(
+        mock: &MockGithub,
+        credentials: &Credentials,
+    ) -> Result<u32, GithubError> {
+        let api = mock.api();
+        let client = build_client(&api, "octo", credentials).unwrap();
+        count_commits_search(&client, &mut ResponseCache::default(), &api, "octo", None).await
+    }
+
+    #[actix_web::test]
+    async fn request_retries_server_errors() {
+        let mock = MockGithub::start().await;
+        mock.on(
+            SEARCH_COMMITS,
+            [
+                MockResponse::status(502),
+                MockResponse::json(serde_json::json!({"total_count": 7})),
+            ],
+        );
+        let commits = count_commits(&mock, &tokens(&["test-token-5xx-ok"])).await;
+        assert_eq!(commits.unwrap(), 7);
+        assert_eq!(mock.hits(SEARCH_COMMITS), 2);
+    }
+
+    #[actix_web::test]
+    async fn request_gives_up_after_repeated_server_errors() {
+        let mock = MockGithub::start().await;
+        mock.on(SEARCH_COMMITS, [MockResponse::status(503)]);
+        let err = count_commits(&mock, &tokens(&["test-token-5xx-fail"]))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            GithubError::Upstream(reqwest::StatusCode::SERVICE_UNAVAILABLE)
+        ));
+        assert_eq!(mock.hits(SEARCH_COMMITS), MAX_RETRIES as usize + 1);
+    }
+
+    #[actix_web::test]
+    async fn request_honours_retry_after() {
+        let mock = MockGithub::start().await;
+        mock.on(
+            SEARCH_COMMITS,
+            [
+                MockResponse::status(403).header("retry-after", 0),
+                MockResponse::json(serde_json::json!({"total_count": 3})),
+            ],
+        );
+        let commits = count_commits(&mock, &tokens(&["test-token-retry-after"])).await;
+        assert_eq!(commits.unwrap(), 3);
+        assert_eq!(mock.hits(SEARCH_COMMITS), 2);
+    }
+
+    #[actix_web::test]
+    async fn request_waits_for_primary_rate_limit_reset() {
+        let mock = MockGithub::start().await;
+        mock.on(
+            SEARCH_COMMITS,
+            [
+                MockResponse::rate_limited(0),
+                MockResponse::json(serde_json::json!({"total_count": 5})),
+            ],
+        );
+        let commits = count_commits(&mock, &tokens(&["test-token-reset"])).await;
+        assert_eq!(commits.unwrap(), 5);
+    }
+
+    #[actix_web::test]
+    async fn request_fails_fast_on_distant_rate_limit_reset() {
+        let mock = MockGithub::start().await;
+        mock.on(SEARCH_COMMITS, [MockResponse::rate_limited(3600)]);
+        let err = count_commits(&mock, &tokens(&["test-token-distant-reset"]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GithubError::RateLimited { until: Some(_) }));
+        assert_eq!(mock.hits(SEARCH_COMMITS), 1);
+    }
+
+    #[actix_web::test]
+    async fn request_rotates_to_token_with_budget() {
+        let mock = MockGithub::start().await;
+        mock.on(
+            SEARCH_COMMITS,
+            [
+                MockResponse::rate_limited(3600),
+                MockResponse::json(serde_json::json!({"total_count": 9})),
+            ],
+        );
+        let credentials = tokens(&["test-token-rotate-a", "test-token-rotate-b"]);
+        assert_eq!(count_commits(&mock, &credentials).await.unwrap(), 9);
+        let authorizations: Vec<String> = mock
+            .requests()
+            .iter()
+            .map(|r| r.headers["authorization"].clone())
+            .collect();
+        assert_eq!(
+            authorizations,
+            ["Bearer test-token-rotate-a", "Bearer test-token-rotate-b"]
+        );
+    }
+
+    #[actix_web::test]
+    async fn request_replays_cached_body_on_304() {
+        let mock = MockGithub::start().await;
+        mock.on(
+            SEARCH_COMMITS,
+            [
+                MockResponse::json(serde_json::json!({"total_count": 11})).header("etag", "\"v1\""),
+                MockResponse::status(304),
+            ],
+        );
+        let api = mock.api();
+        let credentials = tokens(&["test-token-etag"]);
+        let client = build_client(&api, "octo", &credentials).unwrap();
+        let mut cache = ResponseCache::default();
+        for _ in 0..2 {
+            let commits = count_commits_search(&client, &mut cache, &api, "octo", None).await;
+            assert_eq!(commits.unwrap(), 11);
+        }
+        assert_eq!(mock.requests()[1].headers["if-none-match"], "\"v1\"");
+    }
+
+    #[actix_web::test]
+    async fn request_maps_unauthorized_to_bad_credentials() {
+        let mock = MockGithub::start().await;
+        mock.on(SEARCH_COMMITS, [MockResponse::status(401)]);
+        let err = count_commits(&mock, &tokens(&["test-token-401"]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GithubError::BadCredentials(_)));
+    }
+
+    #[actix_web::test]
+    async fn stats_request_unknown_user_is_not_found() {
+        let mock = MockGithub::start().await;
+        mock.on(SEARCH_REPOS, [MockResponse::status(422)]);
+        let err = Stats::request(
+            &mock.api(),
+            "octo",
+            &tokens(&["test-token-422"]),
+            &make_options(false, false),
+            None,
+            &mut ResponseCache::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, GithubError::UserNotFound(user) if user == "octo"));
+    }
+
+    async fn count_commits_in_2024(
+        mock: &MockGithub,
+        credentials: &Credentials,
+    ) -> Result<u32, GithubError> {
+        let api = mock.api();
+        let client = build_client(&api, "octo", credentials).unwrap();
+        count_commits_graphql(&client, &api, "octo", Some(2024)).await
+    }
+
+    #[actix_web::test]
+    async fn graphql_not_found_is_user_not_found() {
+        let mock = MockGithub::start().await;
+        mock.on(
+            "/graphql",
+            [MockResponse::json(serde_json::json!({
+                "data": {"user": null},
+                "errors": [{
+                    "type": "NOT_FOUND",
+                    "message": "Could not resolve to a User with the login of 'octo'.",
+                }],
+            }))],
+        );
+        let err = count_commits_in_2024(&mock, &tokens(&["test-token-gql-404"]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GithubError::UserNotFound(user) if user == "octo"));
+    }
+
+    #[actix_web::test]
+    async fn graphql_rate_limited_is_rate_limited() {
+        let mock = MockGithub::start().await;
+        let reset = now_secs().unwrap() + 3600;
+        mock.on(
+            "/graphql",
+            [MockResponse::json(serde_json::json!({
+                "errors": [{"type": "RATE_LIMITED", "message": "API rate limit exceeded"}],
+            }))
+            .header("x-ratelimit-remaining", 0)
+            .header("x-ratelimit-limit", 5000)
+            .header("x-ratelimit-resource", "graphql")
+            .header("x-ratelimit-reset", reset)],
+        );
+        let err = count_commits_in_2024(&mock, &tokens(&["test-token-gql-limit"]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GithubError::RateLimited { until: Some(t) } if t == reset));
+    }
+
+    #[actix_web::test]
+    async fn stats_request_refused_when_budget_is_exhausted() {
+        let mock = MockGithub::start().await;
+        let reset = now_secs().unwrap() + 3600;
+        rate_limit::record(
+            &token_pool::key("test-token-budget"),
+            &token_pool::mask("test-token-budget"),
+            "search",
+            rate_limit::Budget {
+                remaining: Some(0),
+                limit: Some(30),
+                used: Some(30),
+                reset: Some(reset),
+            },
+        );
+        let err = Stats::request(
+            &mock.api(),
+            "octo",
+            &tokens(&["test-token-budget"]),
+            &make_options(false, false),
+            None,
+            &mut ResponseCache::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, GithubError::RateLimited { until: Some(t) } if t == reset));
+        assert!(mock.requests().is_empty());
+    }
 }
@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use log::{debug, info};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::date::parse_timestamp;
+use crate::github::{GithubApi, GithubError};
+
+/// GitHub rejects app JWTs valid for more than ten minutes.
+const JWT_LIFETIME_SECS: u64 = 540;
+/// Backdates `iat` to tolerate clock drift between this host and GitHub.
+const JWT_CLOCK_DRIFT_SECS: u64 = 60;
+/// Installation tokens are replaced this long before they expire.
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(300);
+
+/// Credentials of a GitHub App installed on the user's account.
+#[derive(Debug, Clone)]
+pub struct GithubApp {
+    pub app_id: String,
+    /// PEM-encoded RSA private key generated for the app.
+    pub private_key: PathBuf,
+    /// Looked up from the user's account when not given.
+    pub installation_id: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct Claims<'a> {
+    iat: u64,
+    exp: u64,
+    iss: &'a str,
+}
+
+#[derive(Deserialize)]
+struct Installation {
+    id: u64,
+}
+
+#[derive(Deserialize)]
+struct AccessToken {
+    token: String,
+    expires_at: String,
+}
+
+struct InstallationToken {
+    token: String,
+    expires_at: SystemTime,
+}
+
+/// Installation tokens shared by all workers, keyed by app and installation.
+fn tokens() -> &'static Mutex<HashMap<String, InstallationToken>> {
+    static TOKENS: OnceLock<Mutex<HashMap<String, InstallationToken>>> = OnceLock::new();
+    TOKENS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+impl GithubApp {
+    /// Signs the short-lived JWT that authenticates as the app itself.
+    fn jwt(&self, now: u64) -> Result<String, GithubError> {
+        let pem = fs::read(&self.private_key).map_err(|err| {
+            GithubError::BadCredentials(format!(
+                "Failed to read GitHub App private key {}: {err}",
+                self.private_key.display()
+            ))
+        })?;
+        let claims = Claims {
+            iat: now - JWT_CLOCK_DRIFT_SECS,
+            exp: now + JWT_LIFETIME_SECS,
+            iss: &self.app_id,
+        };
+        let key = EncodingKey::from_rsa_pem(&pem).map_err(|err| {
+            GithubError::BadCredentials(format!("Invalid GitHub App private key: {err}"))
+        })?;
+        jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &key).map_err(|err| {
+            GithubError::BadCredentials(format!("Failed to sign GitHub App JWT: {err}"))
+        })
+    }
+
+    fn cache_key(&self, github_user: &str) -> String {
+        match self.installation_id {
+            Some(id) => format!("{}:{id}", self.app_id),
+            None => format!("{}:{}", self.app_id, github_user.to_lowercase()),
+        }
+    }
+
+    /// Returns an installation access token, exchanging a fresh JWT for a new one when
+    /// the cached token is missing or about to expire.
+    pub async fn installation_token(
+        &self,
+        http: &Client,
+        api: &GithubApi,
+        github_user: &str,
+    ) -> Result<String, GithubError> {
+        let key = self.cache_key(github_user);
+        let now = SystemTime::now();
+        if let Some(cached) = tokens().lock().unwrap().get(&key) {
+            if cached.expires_at > now + TOKEN_REFRESH_MARGIN {
+                return Ok(cached.token.clone());
+            }
+        }
+
+        let jwt = self.jwt(now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs())?;
+        let installation_id = match self.installation_id {
+            Some(id) => id,
+            None => {
+                let url = api.url(&format!("/users/{github_user}/installation"));
+                let installation: Installation = send_app_request(http.get(url), &jwt).await?;
+                debug!("Found GitHub App installation {}", installation.id);
+                installation.id
+            }
+        };
+
+        let url = api.url(&format!(
+            "/app/installations/{installation_id}/access_tokens"
+        ));
+        let access: AccessToken = send_app_request(http.post(url), &jwt).await?;
+        let expires_at = parse_timestamp(&access.expires_at).ok_or_else(|| {
+            GithubError::Decode(format!(
+                "Unexpected expires_at timestamp '{}'",
+                access.expires_at
+            ))
+        })?;
+        info!(
+            "Obtained GitHub App installation token valid until {}",
+            access.expires_at
+        );
+
+        tokens().lock().unwrap().insert(
+            key,
+            InstallationToken {
+                token: access.token.clone(),
+                expires_at,
+            },
+        );
+        Ok(access.token)
+    }
+}
+
+async fn send_app_request<T: serde::de::DeserializeOwned>(
+    request: reqwest::RequestBuilder,
+    jwt: &str,
+) -> Result<T, GithubError> {
+    let response = request.bearer_auth(jwt).send().await?;
+    let status = response.status();
+    if status.is_server_error() {
+        return Err(GithubError::Upstream(status));
+    }
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(GithubError::BadCredentials(format!(
+            "GitHub App authentication failed with status {status}: {body}"
+        )));
+    }
+    Ok(response.json().await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_key_prefers_installation_id() {
+        let mut app = GithubApp {
+            app_id: "42".to_string(),
+            private_key: PathBuf::new(),
+            installation_id: Some(7),
+        };
+        assert_eq!(app.cache_key("Octo"), "42:7");
+        app.installation_id = None;
+        assert_eq!(app.cache_key("Octo"), "42:octo");
+    }
+
+    #[test]
+    fn jwt_missing_private_key_fails() {
+        let app = GithubApp {
+            app_id: "42".to_string(),
+            private_key: PathBuf::from("/nonexistent/app.pem"),
+            installation_id: None,
+        };
+        let err = app.jwt(1_700_000_000).unwrap_err();
+        assert!(err.to_string().contains("/nonexistent/app.pem"));
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+/// Outcome of the most recent stats crawl, as shown by `/health`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RefreshOutcome {
+    /// Unix timestamp at which the crawl finished.
+    pub finished_at: u64,
+    pub duration_ms: u64,
+    pub succeeded: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Default)]
+struct State {
+    last_refresh: Option<RefreshOutcome>,
+    last_success: Option<u64>,
+    /// Whether GitHub last accepted each credential, keyed by `Credentials::keys`.
+    credentials: HashMap<String, bool>,
+}
+
+/// State shared by all workers.
+fn state() -> &'static Mutex<State> {
+    static STATE: OnceLock<Mutex<State>> = OnceLock::new();
+    STATE.get_or_init(|| Mutex::new(State::default()))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl State {
+    fn record_refresh(&mut self, finished_at: u64, duration: Duration, error: Option<String>) {
+        if error.is_none() {
+            self.last_success = Some(finished_at);
+        }
+        self.last_refresh = Some(RefreshOutcome {
+            finished_at,
+            duration_ms: duration.as_millis() as u64,
+            succeeded: error.is_none(),
+            error,
+        });
+    }
+}
+
+/// Records a finished stats crawl; `error` is `None` when it succeeded.
+pub fn record_refresh(duration: Duration, error: Option<String>) {
+    state()
+        .lock()
+        .unwrap()
+        .record_refresh(now(), duration, error);
+}
+
+pub fn last_refresh() -> Option<RefreshOutcome> {
+    state().lock().unwrap().last_refresh.clone()
+}
+
+/// Unix timestamp of the last crawl that succeeded.
+pub fn last_success() -> Option<u64> {
+    state().lock().unwrap().last_success
+}
+
+/// Records whether GitHub accepted `credential` on its last request.
+pub fn record_credential(credential: &str, valid: bool) {
+    state()
+        .lock()
+        .unwrap()
+        .credentials
+        .insert(credential.to_string(), valid);
+}
+
+/// `valid` or `invalid` as GitHub last answered, `unknown` before the first request.
+pub fn credential_status(credential: &str) -> &'static str {
+    match state().lock().unwrap().credentials.get(credential) {
+        Some(true) => "valid",
+        Some(false) => "invalid",
+        None => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_refresh_keeps_last_success() {
+        let mut state = State::default();
+        state.record_refresh(100, Duration::from_millis(1500), None);
+        state.record_refresh(
+            200,
+            Duration::from_millis(20),
+            Some(String::from("rate limited")),
+        );
+        let last = state.last_refresh.unwrap();
+        assert!(!last.succeeded);
+        assert_eq!(last.error.as_deref(), Some("rate limited"));
+        assert_eq!((last.finished_at, last.duration_ms), (200, 20));
+        assert_eq!(state.last_success, Some(100));
+    }
+
+    #[test]
+    fn credentials_are_unknown_until_used() {
+        assert_eq!(credential_status("…test-unused"), "unknown");
+        record_credential("…test-bad", false);
+        assert_eq!(credential_status("…test-bad"), "invalid");
+        record_credential("…test-bad", true);
+        assert_eq!(credential_status("…test-bad"), "valid");
+    }
+}
@@ -0,0 +1,224 @@
+use std::cell::Cell;
+use std::future::Future;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::ValueEnum;
+use env_logger::fmt::Formatter;
+use env_logger::Target;
+use log::kv::{self, Key, Source, Value, VisitSource};
+use log::{LevelFilter, Record};
+
+/// Longest `X-Request-Id` accepted from a client or proxy; longer ones are replaced.
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// Per-request state visible to every log line emitted while the request is handled,
+/// including those of the GitHub calls it triggers.
+struct RequestContext {
+    id: String,
+    cache: Cell<Option<&'static str>>,
+}
+
+tokio::task_local! {
+    static REQUEST: RequestContext;
+}
+
+/// Runs `future` as part of request `id`, so its log lines carry the id.
+pub async fn in_request<F: Future>(id: String, future: F) -> F::Output {
+    let context = RequestContext {
+        id,
+        cache: Cell::new(None),
+    };
+    REQUEST.scope(context, future).await
+}
+
+pub fn request_id() -> Option<String> {
+    REQUEST.try_with(|request| request.id.clone()).ok()
+}
+
+/// Records a cache lookup for the current request; one miss makes the request a miss.
+pub fn record_cache(hit: bool) {
+    let _ = REQUEST.try_with(|request| {
+        if request.cache.get() != Some("miss") {
+            request.cache.set(Some(if hit { "hit" } else { "miss" }));
+        }
+    });
+}
+
+/// `hit` or `miss` when the current request looked up cached stats.
+pub fn cache_status() -> Option<&'static str> {
+    REQUEST
+        .try_with(|request| request.cache.get())
+        .ok()
+        .flatten()
+}
+
+/// Keeps an id set by a proxy when it is safe to log, otherwise generates one.
+pub fn request_id_from(header: Option<&str>) -> String {
+    match header {
+        Some(id)
+            if !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LEN
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) =>
+        {
+            id.to_string()
+        }
+        _ => generate_request_id(),
+    }
+}
+
+/// 16 hex digits, unique within the process and unlikely to repeat across restarts.
+fn generate_request_id() -> String {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64)
+        ^ u64::from(std::process::id()) << 32;
+    // splitmix64, so consecutive ids do not look related.
+    let mut z = seed.wrapping_add(
+        NEXT.fetch_add(1, Ordering::Relaxed)
+            .wrapping_mul(0x9e37_79b9_7f4a_7c15),
+    );
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    format!("{:016x}", z ^ (z >> 31))
+}
+
+/// Collects a record's key-values as JSON.
+struct JsonFields(serde_json::Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        self.0.insert(key.to_string(), json_value(&value));
+        Ok(())
+    }
+}
+
+fn json_value(value: &Value) -> serde_json::Value {
+    if let Some(b) = value.to_bool() {
+        b.into()
+    } else if let Some(n) = value.to_u64() {
+        n.into()
+    } else if let Some(n) = value.to_i64() {
+        n.into()
+    } else if let Some(n) = value.to_f64() {
+        n.into()
+    } else {
+        value.to_string().into()
+    }
+}
+
+/// Renders one log line. The fixed fields come first and cannot be overwritten by a
+/// key-value of the same name.
+fn json_line(
+    timestamp: &str,
+    record: &Record,
+    request_id: Option<String>,
+) -> Result<String, kv::Error> {
+    let mut fields = JsonFields(serde_json::Map::new());
+    record.key_values().visit(&mut fields)?;
+    let mut line = serde_json::Map::new();
+    line.insert("timestamp".into(), timestamp.into());
+    line.insert("level".into(), record.level().as_str().into());
+    line.insert("target".into(), record.target().into());
+    line.insert("message".into(), record.args().to_string().into());
+    if let Some(id) = request_id {
+        line.insert("request_id".into(), id.into());
+    }
+    for (key, value) in fields.0 {
+        line.entry(key).or_insert(value);
+    }
+    Ok(serde_json::Value::Object(line).to_string())
+}
+
+fn write_json(buf: &mut Formatter, record: &Record) -> io::Result<()> {
+    let timestamp = buf.timestamp_millis().to_string();
+    let line = json_line(&timestamp, record, request_id()).map_err(io::Error::other)?;
+    writeln!(buf, "{line}")
+}
+
+/// The default key-value format, followed by the request id.
+fn write_text_fields(buf: &mut Formatter, fields: &dyn Source) -> io::Result<()> {
+    env_logger::fmt::default_kv_format(buf, fields)?;
+    match request_id() {
+        Some(id) => write!(buf, " request_id={id}"),
+        None => Ok(()),
+    }
+}
+
+/// Installs the logger. `RUST_LOG` overrides the default `info` level.
+pub fn init(format: LogFormat, target: Target) {
+    let mut builder = env_logger::builder();
+    builder
+        .filter_level(LevelFilter::Info)
+        .parse_default_env()
+        .target(target);
+    match format {
+        LogFormat::Text => builder.format_key_values(write_text_fields),
+        LogFormat::Json => builder.format(write_json),
+    };
+    builder.init();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proxy_request_ids_are_kept_when_safe() {
+        assert_eq!(request_id_from(Some("abc-123_x.y")), "abc-123_x.y");
+        for unsafe_id in ["", "has space", "quote\"", &"x".repeat(65)] {
+            let id = request_id_from(Some(unsafe_id));
+            assert_eq!(id.len(), 16, "{unsafe_id}");
+        }
+        assert_ne!(request_id_from(None), request_id_from(None));
+    }
+
+    #[test]
+    fn json_line_has_stable_fields() {
+        let fields = [
+            ("status", Value::from(200u16)),
+            ("endpoint", Value::from("/stats")),
+            ("message", Value::from("ignored")),
+        ];
+        let record = Record::builder()
+            .level(log::Level::Info)
+            .target("github_stats::access")
+            .args(format_args!("GET /stats 200"))
+            .key_values(&fields)
+            .build();
+        let line = json_line("2026-01-01T00:00:00.000Z", &record, Some("abc".into())).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(json["level"], "INFO");
+        assert_eq!(json["message"], "GET /stats 200");
+        assert_eq!(json["request_id"], "abc");
+        assert_eq!(json["status"], 200);
+        assert_eq!(json["endpoint"], "/stats");
+    }
+
+    #[actix_web::test]
+    async fn request_context_is_scoped_to_the_request() {
+        assert_eq!(request_id(), None);
+        let cache = in_request(String::from("req-1"), async {
+            assert_eq!(request_id().as_deref(), Some("req-1"));
+            record_cache(false);
+            record_cache(true);
+            cache_status()
+        })
+        .await;
+        assert_eq!(cache, Some("miss"));
+        assert_eq!(cache_status(), None);
+    }
+}
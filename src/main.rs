@@ -1,52 +1,146 @@
 use std::{
+    collections::HashMap,
     fs,
+    future::Future,
     io::{self, Error},
-    path::Path,
-    time::{Duration, SystemTime},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use actix_files::NamedFile;
-use actix_governor::{Governor, GovernorConfigBuilder};
 use actix_web::http::{Method, StatusCode};
 use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse},
     get,
-    http::header::{self, CacheControl, CacheDirective},
-    middleware::Logger,
+    http::header::{
+        self, CacheControl, CacheDirective, EntityTag, HeaderName, HeaderValue, HttpDate,
+    },
     post, web,
     web::Data,
-    App, Either, HttpResponse, HttpServer, Responder,
+    App, Either, HttpMessage, HttpRequest, HttpResponse, HttpServer, Responder,
 };
-use config::{ConfigError, Environment};
+use clap::Parser;
+use config::{builder::DefaultState, ConfigBuilder, ConfigError, Environment};
 
 use env_logger::Target;
-use log::{debug, error, info, LevelFilter};
-use serde::{Deserialize, Serialize};
+use log::{debug, error, info};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
+use client_ip::IpNetwork;
+use reload::SharedConfig;
+use request_limit::{Bucket, Limiter};
+
+mod badge;
 mod card;
+mod cli;
+mod client_ip;
+mod date;
 mod github;
+mod github_app;
+mod health;
 mod icons;
 mod language_colors;
+mod logging;
+mod metrics;
+#[cfg(test)]
+mod mock_github;
+mod rate_limit;
+mod reload;
+mod request_limit;
+mod site;
+#[cfg(test)]
+mod snapshots;
 mod stats;
+#[cfg(test)]
+mod test_dir;
 mod themes;
+mod token_pool;
 mod toplangs;
+mod webhook;
 
 const ONE_DAY: u32 = 86400;
 const STATS_CACHE_JSON: &str = "stats_cache.json";
+const RESPONSE_CACHE_JSON: &str = "github_response_cache.json";
+/// How long clients may cache a "user not found" error.
+const NOT_FOUND_CACHE_SECONDS: u32 = 300;
+/// Request id taken from a proxy, or generated, and echoed in the response.
+const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+/// Largest webhook payload accepted. GitHub allows up to 25 MB, but the events that
+/// trigger a refresh are far smaller, and the body is buffered before its signature
+/// can be checked.
+const WEBHOOK_MAX_PAYLOAD: usize = 1024 * 1024;
 
 #[derive(Deserialize, Clone)]
 struct Config {
     cache_seconds: u32,
     github_user: String,
-    github_token: String,
+    /// Comma-separated personal access tokens, rotated when one runs out of quota. Not
+    /// needed when authenticating as a GitHub App.
+    #[serde(deserialize_with = "comma_list")]
+    github_token: Vec<String>,
+    #[serde(deserialize_with = "empty_as_none")]
+    github_app_id: Option<String>,
+    #[serde(deserialize_with = "empty_as_none")]
+    github_app_private_key: Option<PathBuf>,
+    /// Looked up from the user's account when unset.
+    #[serde(deserialize_with = "empty_as_none")]
+    github_app_installation_id: Option<u64>,
+    github_api_url: String,
+    /// Derived from `github_api_url` when unset.
+    #[serde(deserialize_with = "empty_as_none")]
+    github_graphql_url: Option<String>,
+    #[serde(deserialize_with = "empty_as_none")]
+    github_ca_cert: Option<PathBuf>,
     base_url: String,
     cache_path: String,
     ipv4_address: String,
     ipv6_address: String,
     port: u16,
-    ignored_repositories: String,
+    #[serde(deserialize_with = "comma_list")]
+    ignored_repositories: Vec<String>,
+    #[serde(deserialize_with = "comma_list")]
+    ignored_topics: Vec<String>,
+    ignore_archived: bool,
+    ignore_templates: bool,
+    #[serde(deserialize_with = "comma_list")]
+    ignored_languages: Vec<String>,
+    /// Ignore rules whose matches are also left out of the star total.
+    #[serde(deserialize_with = "comma_list")]
+    ignore_affects_stars: Vec<github::IgnoreRule>,
     exclude_forks: bool,
+    include_private: bool,
+    include_archived: bool,
+    include_org_repos: bool,
+    commit_count: github::CommitCountStrategy,
+    #[serde(deserialize_with = "empty_as_none")]
+    commits_year: Option<u16>,
     /// Empty string means the /refresh endpoint is disabled.
     refresh_token: String,
+    /// Empty string means /metrics is served without authentication.
+    metrics_token: String,
+    /// Requests per minute each client may make to the card endpoints; 0 disables the limit.
+    rate_limit_per_minute: u32,
+    rate_limit_burst: u32,
+    rate_limit_refresh_per_minute: u32,
+    rate_limit_refresh_burst: u32,
+    rate_limit_metrics_per_minute: u32,
+    rate_limit_metrics_burst: u32,
+    rate_limit_webhook_per_minute: u32,
+    rate_limit_webhook_burst: u32,
+    /// Clients that are never rate limited, e.g. an image proxy.
+    #[serde(deserialize_with = "comma_list")]
+    rate_limit_exempt: Vec<IpNetwork>,
+    /// Proxies whose `Forwarded` and `X-Forwarded-For` headers name the client.
+    #[serde(deserialize_with = "comma_list")]
+    trusted_proxies: Vec<IpNetwork>,
+    /// Empty string means the /webhook/github endpoint is disabled.
+    webhook_secret: String,
+    /// How long a webhook-triggered refresh waits for further events.
+    webhook_debounce_seconds: u32,
+    /// Custom themes by name, only settable in the config file.
+    #[serde(default)]
+    themes: HashMap<String, themes::Theme>,
 }
 
 impl std::fmt::Debug for Config {
@@ -54,14 +148,40 @@ impl std::fmt::Debug for Config {
         f.debug_struct("Config")
             .field("cache_seconds", &self.cache_seconds)
             .field("github_user", &self.github_user)
-            .field("github_token", &"[REDACTED]")
+            .field(
+                "github_token",
+                &self
+                    .github_token
+                    .iter()
+                    .map(|t| token_pool::mask(t))
+                    .collect::<Vec<_>>(),
+            )
+            .field("github_app_id", &self.github_app_id)
+            .field("github_app_private_key", &self.github_app_private_key)
+            .field(
+                "github_app_installation_id",
+                &self.github_app_installation_id,
+            )
+            .field("github_api_url", &self.github_api_url)
+            .field("github_graphql_url", &self.github_graphql_url)
+            .field("github_ca_cert", &self.github_ca_cert)
             .field("base_url", &self.base_url)
             .field("cache_path", &self.cache_path)
             .field("ipv4_address", &self.ipv4_address)
             .field("ipv6_address", &self.ipv6_address)
             .field("port", &self.port)
             .field("ignored_repositories", &self.ignored_repositories)
+            .field("ignored_topics", &self.ignored_topics)
+            .field("ignore_archived", &self.ignore_archived)
+            .field("ignore_templates", &self.ignore_templates)
+            .field("ignored_languages", &self.ignored_languages)
+            .field("ignore_affects_stars", &self.ignore_affects_stars)
             .field("exclude_forks", &self.exclude_forks)
+            .field("include_private", &self.include_private)
+            .field("include_archived", &self.include_archived)
+            .field("include_org_repos", &self.include_org_repos)
+            .field("commit_count", &self.commit_count)
+            .field("commits_year", &self.commits_year)
             .field(
                 "refresh_token",
                 if self.refresh_token.is_empty() {
@@ -70,30 +190,356 @@ impl std::fmt::Debug for Config {
                     &"[REDACTED]"
                 },
             )
+            .field(
+                "metrics_token",
+                if self.metrics_token.is_empty() {
+                    &"(not set)"
+                } else {
+                    &"[REDACTED]"
+                },
+            )
+            .field("rate_limit_per_minute", &self.rate_limit_per_minute)
+            .field("rate_limit_burst", &self.rate_limit_burst)
+            .field(
+                "rate_limit_refresh_per_minute",
+                &self.rate_limit_refresh_per_minute,
+            )
+            .field("rate_limit_refresh_burst", &self.rate_limit_refresh_burst)
+            .field(
+                "rate_limit_metrics_per_minute",
+                &self.rate_limit_metrics_per_minute,
+            )
+            .field("rate_limit_metrics_burst", &self.rate_limit_metrics_burst)
+            .field(
+                "rate_limit_webhook_per_minute",
+                &self.rate_limit_webhook_per_minute,
+            )
+            .field("rate_limit_webhook_burst", &self.rate_limit_webhook_burst)
+            .field("rate_limit_exempt", &self.rate_limit_exempt)
+            .field("trusted_proxies", &self.trusted_proxies)
+            .field(
+                "webhook_secret",
+                if self.webhook_secret.is_empty() {
+                    &"(not set)"
+                } else {
+                    &"[REDACTED]"
+                },
+            )
+            .field("webhook_debounce_seconds", &self.webhook_debounce_seconds)
+            .field("themes", &self.themes.keys().collect::<Vec<_>>())
             .finish()
     }
 }
 
+/// Deserializes an optional value where an empty string (e.g. `STATS_COMMITS_YEAR=`) means unset.
+fn empty_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    let value = String::deserialize(deserializer)?;
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ListOrString {
+    List(Vec<String>),
+    String(String),
+}
+
+/// Deserializes a list given either as an array (config file) or as a comma-separated
+/// string (environment), skipping empty entries.
+fn comma_list<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    let entries = match ListOrString::deserialize(deserializer)? {
+        ListOrString::List(entries) => entries,
+        ListOrString::String(value) => value.split(',').map(str::to_string).collect(),
+    };
+    entries
+        .iter()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse().map_err(serde::de::Error::custom))
+        .collect()
+}
+
+/// Config file keys (`section.key`) and the setting each one maps to. Environment
+/// variables use the setting name, so `STATS_IGNORED_TOPICS` overrides `filters.ignored_topics`.
+const FILE_KEYS: &[(&str, &str)] = &[
+    ("user.name", "github_user"),
+    ("user.token", "github_token"),
+    ("user.app_id", "github_app_id"),
+    ("user.app_private_key", "github_app_private_key"),
+    ("user.app_installation_id", "github_app_installation_id"),
+    ("github.api_url", "github_api_url"),
+    ("github.graphql_url", "github_graphql_url"),
+    ("github.ca_cert", "github_ca_cert"),
+    ("github.commit_count", "commit_count"),
+    ("filters.ignored_repositories", "ignored_repositories"),
+    ("filters.ignored_topics", "ignored_topics"),
+    ("filters.ignored_languages", "ignored_languages"),
+    ("filters.ignore_archived", "ignore_archived"),
+    ("filters.ignore_templates", "ignore_templates"),
+    ("filters.ignore_affects_stars", "ignore_affects_stars"),
+    ("filters.exclude_forks", "exclude_forks"),
+    ("filters.include_private", "include_private"),
+    ("filters.include_archived", "include_archived"),
+    ("filters.include_org_repos", "include_org_repos"),
+    ("filters.commits_year", "commits_year"),
+    ("cache.seconds", "cache_seconds"),
+    ("cache.path", "cache_path"),
+    ("server.base_url", "base_url"),
+    ("server.ipv4_address", "ipv4_address"),
+    ("server.ipv6_address", "ipv6_address"),
+    ("server.port", "port"),
+    ("server.refresh_token", "refresh_token"),
+    ("server.metrics_token", "metrics_token"),
+    ("server.trusted_proxies", "trusted_proxies"),
+    ("rate_limit.per_minute", "rate_limit_per_minute"),
+    ("rate_limit.burst", "rate_limit_burst"),
+    (
+        "rate_limit.refresh_per_minute",
+        "rate_limit_refresh_per_minute",
+    ),
+    ("rate_limit.refresh_burst", "rate_limit_refresh_burst"),
+    (
+        "rate_limit.metrics_per_minute",
+        "rate_limit_metrics_per_minute",
+    ),
+    ("rate_limit.metrics_burst", "rate_limit_metrics_burst"),
+    (
+        "rate_limit.webhook_per_minute",
+        "rate_limit_webhook_per_minute",
+    ),
+    ("rate_limit.webhook_burst", "rate_limit_webhook_burst"),
+    ("rate_limit.exempt", "rate_limit_exempt"),
+    ("webhook.secret", "webhook_secret"),
+    ("webhook.debounce_seconds", "webhook_debounce_seconds"),
+];
+
+/// Adds where a setting named by `err` can be set, since it may come from either source.
+fn name_config_sources(err: ConfigError) -> ConfigError {
+    let message = err.to_string();
+    let setting = message
+        .split("for key `")
+        .nth(1)
+        .and_then(|rest| rest.split('`').next());
+    match setting.and_then(|setting| FILE_KEYS.iter().find(|(_, s)| *s == setting)) {
+        Some((file_key, setting)) => ConfigError::Message(format!(
+            "{message} (`{file_key}` in the config file, STATS_{} in the environment)",
+            setting.to_uppercase()
+        )),
+        None => err,
+    }
+}
+
 impl Config {
-    pub fn from_env() -> Result<Self, ConfigError> {
+    /// Reads the settings from `file`, if given, overridden by `STATS_*` environment variables.
+    pub fn load(file: Option<&Path>) -> Result<Self, ConfigError> {
+        let mut builder = Self::defaults()?;
+        if let Some(file) = file {
+            builder = Self::with_file(builder, file)?;
+        }
+        builder
+            .add_source(Self::environment())
+            .build()?
+            .try_deserialize::<Config>()
+            .map_err(name_config_sources)?
+            .validated()
+    }
+
+    fn environment() -> Environment {
+        Environment::default()
+            .prefix("STATS")
+            .prefix_separator("_")
+            .separator("__")
+    }
+
+    /// Replaces the defaults with the values from a TOML or YAML config file, so that
+    /// environment variables added afterwards still take precedence.
+    fn with_file(
+        mut builder: ConfigBuilder<DefaultState>,
+        path: &Path,
+    ) -> Result<ConfigBuilder<DefaultState>, ConfigError> {
+        let file = config::Config::builder()
+            .add_source(config::File::from(path))
+            .build()?
+            .try_deserialize::<config::Map<String, config::Value>>()?;
+        for (section, value) in file {
+            if section == "themes" {
+                builder = builder.set_default("themes", value)?;
+                continue;
+            }
+            let table = value.into_table().map_err(|_| {
+                ConfigError::Message(format!(
+                    "`{section}` in {} must be a section",
+                    path.display()
+                ))
+            })?;
+            for (key, value) in table {
+                let file_key = format!("{section}.{key}");
+                let Some((_, setting)) = FILE_KEYS.iter().find(|(k, _)| *k == file_key) else {
+                    return Err(ConfigError::Message(format!(
+                        "unknown key `{file_key}` in {}",
+                        path.display()
+                    )));
+                };
+                builder = builder.set_default(*setting, value)?;
+            }
+        }
+        Ok(builder)
+    }
+
+    /// Builder preloaded with the default of every optional setting.
+    fn defaults() -> Result<ConfigBuilder<DefaultState>, ConfigError> {
         config::Config::builder()
             .set_default("cache_seconds", ONE_DAY)?
+            .set_default("github_token", "")?
+            .set_default("github_app_id", "")?
+            .set_default("github_app_private_key", "")?
+            .set_default("github_app_installation_id", "")?
+            .set_default("github_api_url", "https://api.github.com")?
+            .set_default("github_graphql_url", "")?
+            .set_default("github_ca_cert", "")?
             .set_default("base_url", "")?
             .set_default("cache_path", "")?
             .set_default("ipv4_address", "0.0.0.0")?
             .set_default("ipv6_address", "")?
             .set_default("port", 8080)?
             .set_default("ignored_repositories", "")?
+            .set_default("ignored_topics", "")?
+            .set_default("ignore_archived", false)?
+            .set_default("ignore_templates", false)?
+            .set_default("ignored_languages", "")?
+            .set_default("ignore_affects_stars", "")?
             .set_default("exclude_forks", false)?
+            .set_default("include_private", false)?
+            .set_default("include_archived", true)?
+            .set_default("include_org_repos", false)?
+            .set_default("commit_count", "search")?
+            .set_default("commits_year", "")?
             .set_default("refresh_token", "")?
-            .add_source(
-                Environment::default()
-                    .prefix("STATS")
-                    .prefix_separator("_")
-                    .separator("__"),
-            )
-            .build()?
+            .set_default("metrics_token", "")?
+            .set_default("rate_limit_per_minute", 20)?
+            .set_default("rate_limit_burst", 3)?
+            .set_default("rate_limit_refresh_per_minute", 2)?
+            .set_default("rate_limit_refresh_burst", 2)?
+            .set_default("rate_limit_metrics_per_minute", 30)?
+            .set_default("rate_limit_metrics_burst", 5)?
+            .set_default("rate_limit_webhook_per_minute", 60)?
+            .set_default("rate_limit_webhook_burst", 20)?
+            .set_default("rate_limit_exempt", "")?
+            .set_default("trusted_proxies", "")?
+            .set_default("webhook_secret", "")?
+            .set_default("webhook_debounce_seconds", 60)
+    }
+
+    /// Settings for the user `octo` with a placeholder token, with `overrides` on top.
+    #[cfg(test)]
+    fn for_tests(overrides: &[(&str, &str)]) -> Config {
+        let builder = Self::defaults()
+            .unwrap()
+            .set_override("github_user", "octo")
+            .unwrap()
+            .set_override("github_token", "token")
+            .unwrap();
+        overrides
+            .iter()
+            .fold(builder, |builder, (key, value)| {
+                builder.set_override(*key, *value).unwrap()
+            })
+            .build()
+            .unwrap()
             .try_deserialize()
+            .unwrap()
+    }
+
+    fn validated(self) -> Result<Self, ConfigError> {
+        match (&self.github_app_id, &self.github_app_private_key) {
+            (Some(_), None) => Err(ConfigError::Message(String::from(
+                "github_app_private_key is required when github_app_id is set",
+            ))),
+            (None, _) if self.github_token.is_empty() => Err(ConfigError::Message(String::from(
+                "either github_token or github_app_id must be set",
+            ))),
+            _ => Ok(()),
+        }?;
+        for (setting, per_minute, burst) in [
+            (
+                "rate_limit",
+                self.rate_limit_per_minute,
+                self.rate_limit_burst,
+            ),
+            (
+                "rate_limit_refresh",
+                self.rate_limit_refresh_per_minute,
+                self.rate_limit_refresh_burst,
+            ),
+            (
+                "rate_limit_metrics",
+                self.rate_limit_metrics_per_minute,
+                self.rate_limit_metrics_burst,
+            ),
+            (
+                "rate_limit_webhook",
+                self.rate_limit_webhook_per_minute,
+                self.rate_limit_webhook_burst,
+            ),
+        ] {
+            if per_minute > 0 && burst == 0 {
+                return Err(ConfigError::Message(format!(
+                    "{setting}_burst must be at least 1 while {setting}_per_minute is set"
+                )));
+            }
+        }
+        for (name, theme) in &self.themes {
+            if let Some((field, color)) = theme.invalid_color() {
+                return Err(ConfigError::Message(format!(
+                    "themes.{name}.{field}: '{color}' is not a color"
+                )));
+            }
+        }
+        Ok(self)
+    }
+
+    fn credentials(&self) -> github::Credentials {
+        match (&self.github_app_id, &self.github_app_private_key) {
+            (Some(app_id), Some(private_key)) => github::Credentials::App(github_app::GithubApp {
+                app_id: app_id.clone(),
+                private_key: private_key.clone(),
+                installation_id: self.github_app_installation_id,
+            }),
+            _ => github::Credentials::Tokens(self.github_token.clone()),
+        }
+    }
+
+    fn github_api(&self) -> github::GithubApi {
+        github::GithubApi::new(
+            &self.github_api_url,
+            self.github_graphql_url.as_deref(),
+            self.github_ca_cert.clone(),
+        )
+    }
+
+    fn repo_filter(&self) -> github::RepoFilter {
+        github::RepoFilter {
+            repositories: self.ignored_repositories.clone(),
+            topics: self.ignored_topics.clone(),
+            archived: self.ignore_archived,
+            templates: self.ignore_templates,
+            languages: self.ignored_languages.clone(),
+            affects_stars: self.ignore_affects_stars.clone(),
+        }
     }
 }
 
@@ -101,79 +547,359 @@ fn stats_cache_path(cache_path: &str) -> std::path::PathBuf {
     Path::join(Path::new(cache_path), STATS_CACHE_JSON)
 }
 
-fn read_cached_stats(
+/// Stats together with the modification time of the cache file they were read from.
+struct CachedStats<T = github::Stats> {
+    stats: T,
+    modified: SystemTime,
+}
+
+/// Marker next to a cache file that makes it stale whatever its age, so the file keeps
+/// its real modification time.
+fn stale_marker(path: &Path) -> PathBuf {
+    path.with_extension("stale")
+}
+
+fn read_cached_stats<T: DeserializeOwned>(
     path: &Path,
     max_age_secs: u32,
-) -> Result<Option<github::Stats>, anyhow::Error> {
-    if !path.exists() {
+) -> Result<Option<CachedStats<T>>, anyhow::Error> {
+    if !path.exists() || stale_marker(path).exists() {
         return Ok(None);
     }
     let threshold = SystemTime::now() - Duration::from_secs(max_age_secs.into());
     if let Ok(modified) = fs::metadata(path)?.modified() {
         if modified > threshold {
             let stats = serde_json::from_str(&fs::read_to_string(path)?)?;
-            return Ok(Some(stats));
+            return Ok(Some(CachedStats { stats, modified }));
         }
     }
     Ok(None)
 }
 
-fn write_cached_stats(path: &Path, stats: &github::Stats) -> Result<(), anyhow::Error> {
+/// Reads cached stats regardless of age, e.g. as a baseline for an incremental refresh.
+fn read_stats_snapshot<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let json = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&json)
+        .inspect_err(|err| debug!("Ignoring unreadable stats snapshot: {err}"))
+        .ok()
+}
+
+fn write_cached_stats<T: Serialize>(path: &Path, stats: &T) -> Result<SystemTime, anyhow::Error> {
     fs::write(path, serde_json::to_string(stats)?.as_bytes())?;
-    Ok(())
+    match fs::remove_file(stale_marker(path)) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+        _ => {}
+    }
+    Ok(fs::metadata(path)?.modified()?)
 }
 
-async fn fetch_stats(config: &Config) -> Result<github::Stats, anyhow::Error> {
+async fn fetch_stats(config: &Config) -> Result<CachedStats, anyhow::Error> {
     let path = stats_cache_path(&config.cache_path);
     match read_cached_stats(&path, config.cache_seconds) {
-        Ok(Some(stats)) => {
+        Ok(Some(cached)) => {
             debug!("Serving stats from cache");
-            return Ok(stats);
+            metrics::cache_lookup("stats", true);
+            logging::record_cache(true);
+            return Ok(cached);
         }
         Ok(None) => {}
         Err(err) => debug!("Stats cache unreadable: {err}"),
     }
+    metrics::cache_lookup("stats", false);
+    logging::record_cache(false);
+    crawl_stats(config).await
+}
 
+/// Crawls GitHub whatever the age of the cached stats, which are only replaced once the
+/// crawl succeeds.
+async fn crawl_stats(config: &Config) -> Result<CachedStats, anyhow::Error> {
+    let path = stats_cache_path(&config.cache_path);
+    let generation = reload::generation();
+    let response_cache_path = Path::join(Path::new(&config.cache_path), RESPONSE_CACHE_JSON);
+    let mut response_cache = github::ResponseCache::load(&response_cache_path);
+    let previous = read_stats_snapshot(&path);
+    let filter = config.repo_filter();
+    let options = github::CrawlOptions {
+        filter: &filter,
+        exclude_forks: config.exclude_forks,
+        include_private: config.include_private,
+        include_archived: config.include_archived,
+        include_org_repos: config.include_org_repos,
+        commit_count: config.commit_count,
+        commits_year: config.commits_year,
+    };
+    let started = Instant::now();
     let fetched = github::Stats::request(
+        &config.github_api(),
         &config.github_user,
-        &config.github_token,
-        &config.ignored_repositories,
-        config.exclude_forks,
+        &config.credentials(),
+        &options,
+        previous.as_ref(),
+        &mut response_cache,
     )
-    .await?;
+    .await;
+    metrics::crawl("stats", started.elapsed(), fetched.is_ok());
+    health::record_refresh(
+        started.elapsed(),
+        fetched.as_ref().err().map(ToString::to_string),
+    );
+    let fetched = fetched?;
+    if let Err(err) = response_cache.save(&response_cache_path) {
+        debug!("Failed to write GitHub response cache: {err}");
+    }
+    if generation != reload::generation() {
+        info!("Configuration changed during the crawl, not caching its stats");
+        return Ok(CachedStats {
+            stats: fetched,
+            modified: SystemTime::now(),
+        });
+    }
 
-    if let Err(err) = write_cached_stats(&path, &fetched) {
-        debug!("Failed to write stats cache: {err}");
+    let modified = match write_cached_stats(&path, &fetched) {
+        Ok(modified) => {
+            metrics::stats_refreshed();
+            modified
+        }
+        Err(err) => {
+            debug!("Failed to write stats cache: {err}");
+            SystemTime::now()
+        }
+    };
+
+    Ok(CachedStats {
+        stats: fetched,
+        modified,
+    })
+}
+
+fn window_cache_path(cache_path: &str, window: &github::Window) -> std::path::PathBuf {
+    Path::join(
+        Path::new(cache_path),
+        format!("stats_window_{}_{}.json", window.since, window.until),
+    )
+}
+
+/// Fetches contribution counts for `window`, cached in a file per window.
+async fn fetch_window_stats(
+    config: &Config,
+    window: &github::Window,
+) -> Result<CachedStats<github::WindowStats>, anyhow::Error> {
+    let path = window_cache_path(&config.cache_path, window);
+    match read_cached_stats(&path, config.cache_seconds) {
+        Ok(Some(cached)) => {
+            debug!("Serving window stats from cache");
+            metrics::cache_lookup("window", true);
+            logging::record_cache(true);
+            return Ok(cached);
+        }
+        Ok(None) => {}
+        Err(err) => debug!("Window stats cache unreadable: {err}"),
+    }
+    metrics::cache_lookup("window", false);
+    logging::record_cache(false);
+    crawl_window_stats(config, window).await
+}
+
+/// Fetches contribution counts for `window` whatever the age of its cache file, which
+/// is only replaced once the fetch succeeds.
+async fn crawl_window_stats(
+    config: &Config,
+    window: &github::Window,
+) -> Result<CachedStats<github::WindowStats>, anyhow::Error> {
+    let path = window_cache_path(&config.cache_path, window);
+    let generation = reload::generation();
+    let started = Instant::now();
+    let fetched = github::WindowStats::request(
+        &config.github_api(),
+        &config.github_user,
+        &config.credentials(),
+        window,
+    )
+    .await;
+    metrics::crawl("window", started.elapsed(), fetched.is_ok());
+    let fetched = fetched?;
+    if generation != reload::generation() {
+        info!("Configuration changed during the crawl, not caching its window stats");
+        return Ok(CachedStats {
+            stats: fetched,
+            modified: SystemTime::now(),
+        });
     }
 
-    Ok(fetched)
+    // Every since/until pair gets its own file, so old ones make room for new ones.
+    prune_window_caches(&config.cache_path, MAX_WINDOW_CACHES - 1);
+    let modified = match write_cached_stats(&path, &fetched) {
+        Ok(modified) => modified,
+        Err(err) => {
+            debug!("Failed to write window stats cache: {err}");
+            SystemTime::now()
+        }
+    };
+
+    Ok(CachedStats {
+        stats: fetched,
+        modified,
+    })
+}
+
+/// Computes a strong entity tag from the rendered response body.
+fn body_etag(body: &str) -> EntityTag {
+    let digest = Sha256::digest(body.as_bytes());
+    let hex: String = digest[..16].iter().map(|b| format!("{b:02x}")).collect();
+    EntityTag::new_strong(hex)
+}
+
+/// Evaluates `If-None-Match` / `If-Modified-Since` against the current representation.
+///
+/// Per RFC 9110 §13.2.2, `If-Modified-Since` is ignored whenever `If-None-Match` is present.
+fn is_not_modified(req: &HttpRequest, etag: &EntityTag, last_modified: SystemTime) -> bool {
+    if let Some(if_none_match) = req.get_header::<header::IfNoneMatch>() {
+        return match if_none_match {
+            header::IfNoneMatch::Any => true,
+            header::IfNoneMatch::Items(tags) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        };
+    }
+    if let Some(header::IfModifiedSince(since)) = req.get_header::<header::IfModifiedSince>() {
+        // HTTP dates have one-second resolution; compare at that precision.
+        let last_modified = SystemTime::from(HttpDate::from(last_modified));
+        return last_modified <= SystemTime::from(since);
+    }
+    false
 }
 
-fn svg_response(svg: String, cache_seconds: u32) -> HttpResponse {
+fn svg_response(
+    req: &HttpRequest,
+    svg: String,
+    last_modified: SystemTime,
+    cache_seconds: u32,
+) -> HttpResponse {
+    let etag = body_etag(&svg);
+    // The card also depends on the configuration, so a reload makes it newer.
+    let last_modified = last_modified.max(reload::loaded_at());
+    let cache_control = CacheControl(vec![
+        CacheDirective::Public,
+        CacheDirective::MaxAge(cache_seconds / 2),
+        CacheDirective::SMaxAge(cache_seconds),
+        CacheDirective::Extension(
+            String::from("stale-while-revalidate"),
+            Some(format!("{ONE_DAY}")),
+        ),
+    ]);
+
+    if is_not_modified(req, &etag, last_modified) {
+        return HttpResponse::NotModified()
+            .insert_header(header::ETag(etag))
+            .insert_header(header::LastModified(last_modified.into()))
+            .insert_header(cache_control)
+            .finish();
+    }
+
     HttpResponse::Ok()
         .insert_header(header::ContentType(mime::IMAGE_SVG))
         .insert_header(("X-Content-Type-Options", "nosniff"))
-        .insert_header(CacheControl(vec![
-            CacheDirective::Public,
-            CacheDirective::MaxAge(cache_seconds / 2),
-            CacheDirective::SMaxAge(cache_seconds),
-            CacheDirective::Extension(
-                String::from("stale-while-revalidate"),
-                Some(format!("{ONE_DAY}")),
-            ),
-        ]))
+        .insert_header(header::ETag(etag))
+        .insert_header(header::LastModified(last_modified.into()))
+        .insert_header(cache_control)
         .body(svg)
 }
 
-fn error_svg_response(message: &str, query: &CardQuery) -> HttpResponse {
+/// SVG response without caching headers, used for error placeholders.
+fn uncached_svg_response(svg: String) -> HttpResponse {
     HttpResponse::Ok()
         .insert_header(header::ContentType(mime::IMAGE_SVG))
         .insert_header(("X-Content-Type-Options", "nosniff"))
-        .body(card::render_error_card(
-            message,
-            query.width(),
-            &query.theme(),
-        ))
+        .body(svg)
+}
+
+fn error_svg_response(message: &str, query: &CardQuery) -> HttpResponse {
+    uncached_svg_response(card::render_error_card(
+        message,
+        query.width(),
+        &query.theme(),
+    ))
+}
+
+/// How a failed fetch is reported to clients.
+struct FetchFailure {
+    message: String,
+    badge_message: &'static str,
+    status: StatusCode,
+    cache_control: CacheControl,
+    retry_after: Option<u64>,
+}
+
+/// Classifies a fetch error; anything that is not a [`github::GithubError`] is reported
+/// with `fallback` as an internal error.
+fn fetch_failure(err: &anyhow::Error, fallback: &str) -> FetchFailure {
+    let no_store = CacheControl(vec![CacheDirective::NoStore]);
+    let Some(err) = err.downcast_ref::<github::GithubError>() else {
+        return FetchFailure {
+            message: fallback.to_string(),
+            badge_message: "unavailable",
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            cache_control: no_store,
+            retry_after: None,
+        };
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let retry_after = err.retry_after(now);
+    let (status, cache_control) = match err {
+        github::GithubError::RateLimited { .. } => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            match retry_after {
+                Some(secs) => CacheControl(vec![
+                    CacheDirective::Public,
+                    CacheDirective::MaxAge(secs.min(u64::from(u32::MAX)) as u32),
+                ]),
+                None => no_store,
+            },
+        ),
+        github::GithubError::StatsPending(_) => (StatusCode::SERVICE_UNAVAILABLE, no_store),
+        github::GithubError::UserNotFound(_) => (
+            StatusCode::NOT_FOUND,
+            CacheControl(vec![
+                CacheDirective::Public,
+                CacheDirective::MaxAge(NOT_FOUND_CACHE_SECONDS),
+            ]),
+        ),
+        github::GithubError::Network(_)
+        | github::GithubError::Upstream(_)
+        | github::GithubError::Decode(_)
+        | github::GithubError::Incomplete(_)
+        | github::GithubError::Status(_) => (StatusCode::BAD_GATEWAY, no_store),
+        github::GithubError::BadCredentials(_) | github::GithubError::Other(_) => {
+            (StatusCode::INTERNAL_SERVER_ERROR, no_store)
+        }
+    };
+    FetchFailure {
+        message: err.user_message(),
+        badge_message: err.short_message(),
+        status,
+        cache_control,
+        retry_after,
+    }
+}
+
+impl FetchFailure {
+    fn response(&self, content_type: mime::Mime, body: String) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status);
+        response
+            .insert_header(header::ContentType(content_type))
+            .insert_header(("X-Content-Type-Options", "nosniff"))
+            .insert_header(self.cache_control.clone());
+        if let Some(secs) = self.retry_after {
+            response.insert_header((header::RETRY_AFTER, secs.to_string()));
+        }
+        response.body(body)
+    }
+
+    fn svg_response(&self, query: &CardQuery) -> HttpResponse {
+        let svg = card::render_error_card(&self.message, query.width(), &query.theme());
+        self.response(mime::IMAGE_SVG, svg)
+    }
 }
 
 fn json_response(json: String) -> HttpResponse {
@@ -188,8 +914,20 @@ struct CardQuery {
     theme: Option<String>,
     lang_count: Option<usize>,
     width: Option<f64>,
+    private_marker: Option<bool>,
+    since: Option<String>,
+    until: Option<String>,
+    year: Option<i32>,
 }
 
+/// Contributions before GitHub's launch cannot exist.
+const EARLIEST_WINDOW_YEAR: i32 = 2008;
+/// Windows are fetched with one GraphQL query per calendar year they touch, so their span
+/// is capped to bound the queries an unauthenticated request can cause.
+const MAX_WINDOW_YEARS: i32 = 10;
+/// Most window caches kept on disk; the least recently fetched are deleted first.
+const MAX_WINDOW_CACHES: usize = 50;
+
 impl CardQuery {
     fn theme(&self) -> themes::Theme {
         themes::from_name(self.theme.as_deref().unwrap_or("dark"))
@@ -202,6 +940,61 @@ impl CardQuery {
     fn lang_count(&self) -> usize {
         self.lang_count.map(|c| c.clamp(1, 100)).unwrap_or(10)
     }
+
+    /// Appends an "includes private" marker when requested and the stats contain private repos.
+    fn title(&self, title: &str, includes_private: bool) -> String {
+        if self.private_marker.unwrap_or(false) && includes_private {
+            format!("{title} (incl. private)")
+        } else {
+            title.to_string()
+        }
+    }
+
+    /// Resolves `year` or `since`/`until` into a time window; `None` means lifetime totals.
+    fn window(&self) -> Result<Option<github::Window>, String> {
+        let today = date::Date::today();
+        let (since, until) = match (self.year, &self.since, &self.until) {
+            (None, None, None) => return Ok(None),
+            (Some(_), Some(_), _) | (Some(_), _, Some(_)) => {
+                return Err(String::from("Use either year or since/until"))
+            }
+            (Some(year), None, None) => {
+                if !(EARLIEST_WINDOW_YEAR..=today.year).contains(&year) {
+                    return Err(format!("Invalid year: {year}"));
+                }
+                (
+                    date::Date::first_of_year(year),
+                    date::Date::last_of_year(year),
+                )
+            }
+            (None, None, Some(_)) => return Err(String::from("until requires since")),
+            (None, Some(since), until) => {
+                let parse = |s: &str| {
+                    date::Date::parse(s)
+                        .ok_or_else(|| format!("Invalid date: {s} (use YYYY-MM-DD)"))
+                };
+                let since = parse(since)?;
+                let until = match until {
+                    Some(until) => parse(until)?,
+                    None => today,
+                };
+                if since > today || until > today {
+                    return Err(String::from("since and until must not be in the future"));
+                }
+                (since, until)
+            }
+        };
+        let since = since.max(date::Date::first_of_year(EARLIEST_WINDOW_YEAR));
+        if since > until {
+            return Err(String::from("since must not be after until"));
+        }
+        if until.year - since.year >= MAX_WINDOW_YEARS {
+            return Err(format!(
+                "A window may span at most {MAX_WINDOW_YEARS} calendar years"
+            ));
+        }
+        Ok(Some(github::Window { since, until }))
+    }
 }
 
 #[derive(Serialize)]
@@ -210,38 +1003,113 @@ struct Endpoints {
     stats_url: String,
     languages_url: String,
     combined_url: String,
+    badge_url: String,
+    repositories_url: String,
     refresh_url: String,
+    webhook_url: String,
     health_url: String,
+    health_live_url: String,
+    health_ready_url: String,
+    metrics_url: String,
     favicon_url: String,
 }
 
 #[get("/")]
-async fn root_endpoint(config: Data<Config>) -> Result<HttpResponse, Error> {
+async fn root_endpoint(config: Data<SharedConfig>) -> Result<HttpResponse, Error> {
+    let config = config.current();
     let endpoints = Endpoints {
         root_url: format!("{}/", config.base_url),
         stats_url: format!("{}/stats", config.base_url),
         languages_url: format!("{}/languages", config.base_url),
         combined_url: format!("{}/combined", config.base_url),
+        badge_url: format!("{}/badge/{{metric}}", config.base_url),
+        repositories_url: format!("{}/repositories", config.base_url),
         refresh_url: format!("{}/refresh", config.base_url),
+        webhook_url: format!("{}/webhook/github", config.base_url),
         health_url: format!("{}/health", config.base_url),
+        health_live_url: format!("{}/health/live", config.base_url),
+        health_ready_url: format!("{}/health/ready", config.base_url),
+        metrics_url: format!("{}/metrics", config.base_url),
         favicon_url: format!("{}/favicon.ico", config.base_url),
     };
     Ok(json_response(serde_json::to_string_pretty(&endpoints)?))
 }
 
 #[get("/stats")]
-async fn stats_endpoint(config: Data<Config>, query: web::Query<CardQuery>) -> impl Responder {
-    match build_stats_svg(&config, &query).await {
-        Ok(svg) => svg_response(svg, config.cache_seconds),
+async fn stats_endpoint(
+    req: HttpRequest,
+    config: Data<SharedConfig>,
+    query: web::Query<CardQuery>,
+) -> impl Responder {
+    let config = config.current();
+    let window = match query.window() {
+        Ok(window) => window,
+        Err(message) => return error_svg_response(&message, &query),
+    };
+    if let Some(window) = window {
+        return match fetch_window_stats(&config, &window).await {
+            Ok(cached) => svg_response(
+                &req,
+                build_window_stats_svg(&cached.stats, &window, &query),
+                cached.modified,
+                config.cache_seconds,
+            ),
+            Err(err) => {
+                error!("Failed to render /stats card for {window:?}: {err:#}");
+                fetch_failure(&err, "Failed to fetch GitHub stats").svg_response(&query)
+            }
+        };
+    }
+
+    match fetch_stats(&config).await {
+        Ok(cached) => svg_response(
+            &req,
+            build_stats_svg(&cached.stats, &query),
+            cached.modified,
+            config.cache_seconds,
+        ),
         Err(err) => {
             error!("Failed to render /stats card: {err:#}");
-            error_svg_response("Failed to fetch GitHub stats", &query)
+            fetch_failure(&err, "Failed to fetch GitHub stats").svg_response(&query)
         }
     }
 }
 
-async fn build_stats_svg(config: &Config, query: &CardQuery) -> Result<String, anyhow::Error> {
-    let card_stats = fetch_stats(config).await?;
+/// Card title suffix for a window, e.g. `2025` or `2025-01-01 – 2025-06-30`.
+fn window_label(window: &github::Window) -> String {
+    if window.is_single_year() {
+        window.since.year.to_string()
+    } else {
+        format!("{} – {}", window.since, window.until)
+    }
+}
+
+fn build_window_stats_svg(
+    window_stats: &github::WindowStats,
+    window: &github::Window,
+    query: &CardQuery,
+) -> String {
+    let theme = query.theme();
+    let width = query.width();
+    let x_offset = 25.0_f64;
+    let y_offset = 20.0_f64;
+    let gap = 30.0_f64;
+    let content_width = width - 2.0 * x_offset;
+
+    let rendered_stats = stats::render_window_stats(&theme, window_stats, content_width);
+
+    card::render_card(
+        vec![rendered_stats],
+        x_offset,
+        y_offset,
+        gap,
+        width,
+        &format!("Stats ({})", window_label(window)),
+        &theme,
+    )
+}
+
+fn build_stats_svg(card_stats: &github::Stats, query: &CardQuery) -> String {
     let theme = query.theme();
     let width = query.width();
     let x_offset = 25.0_f64;
@@ -253,33 +1121,67 @@ async fn build_stats_svg(config: &Config, query: &CardQuery) -> Result<String, a
         &theme,
         card_stats.total_stars,
         card_stats.total_commits,
+        card_stats.commits_year,
         content_width,
     );
 
-    Ok(card::render_card(
+    card::render_card(
         vec![rendered_stats],
         x_offset,
         y_offset,
         gap,
         width,
-        "Stats",
+        &query.title("Stats", card_stats.includes_private),
         &theme,
-    ))
+    )
 }
 
 #[get("/combined")]
-async fn combined_endpoint(config: Data<Config>, query: web::Query<CardQuery>) -> impl Responder {
-    match build_combined_svg(&config, &query).await {
-        Ok(svg) => svg_response(svg, config.cache_seconds),
+async fn combined_endpoint(
+    req: HttpRequest,
+    config: Data<SharedConfig>,
+    query: web::Query<CardQuery>,
+) -> impl Responder {
+    let config = config.current();
+    let window = match query.window() {
+        Ok(window) => window,
+        Err(message) => return error_svg_response(&message, &query),
+    };
+    let cached = match fetch_stats(&config).await {
+        Ok(cached) => cached,
         Err(err) => {
             error!("Failed to render /combined card: {err:#}");
-            error_svg_response("Failed to fetch GitHub stats", &query)
+            return fetch_failure(&err, "Failed to fetch GitHub stats").svg_response(&query);
         }
-    }
+    };
+    let windowed = match &window {
+        Some(window) => match fetch_window_stats(&config, window).await {
+            Ok(windowed) => Some((windowed, window)),
+            Err(err) => {
+                error!("Failed to render /combined card for {window:?}: {err:#}");
+                return fetch_failure(&err, "Failed to fetch GitHub stats").svg_response(&query);
+            }
+        },
+        None => None,
+    };
+
+    let last_modified = windowed
+        .as_ref()
+        .map_or(cached.modified, |(w, _)| w.modified.max(cached.modified));
+    let window_stats = windowed.as_ref().map(|(w, window)| (&w.stats, *window));
+    svg_response(
+        &req,
+        build_combined_svg(&cached.stats, window_stats, &query),
+        last_modified,
+        config.cache_seconds,
+    )
 }
 
-async fn build_combined_svg(config: &Config, query: &CardQuery) -> Result<String, anyhow::Error> {
-    let card_stats = fetch_stats(config).await?;
+fn build_combined_svg(
+    card_stats: &github::Stats,
+    window_stats: Option<(&github::WindowStats, &github::Window)>,
+    query: &CardQuery,
+) -> String {
     let theme = query.theme();
     let width = query.width();
     let x_offset = 25.0_f64;
@@ -287,12 +1189,22 @@ async fn build_combined_svg(config: &Config, query: &CardQuery) -> Result<String
     let gap = 30.0_f64;
     let content_width = width - 2.0 * x_offset;
 
-    let rendered_stats = stats::render_stats(
-        &theme,
-        card_stats.total_stars,
-        card_stats.total_commits,
-        content_width,
-    );
+    let (rendered_stats, title) = match window_stats {
+        Some((window_stats, window)) => (
+            stats::render_window_stats(&theme, window_stats, content_width),
+            format!("Stats ({})", window_label(window)),
+        ),
+        None => (
+            stats::render_stats(
+                &theme,
+                card_stats.total_stars,
+                card_stats.total_commits,
+                card_stats.commits_year,
+                content_width,
+            ),
+            String::from("Stats"),
+        ),
+    };
     let rendered_langs = toplangs::render_top_languages(
         &theme,
         x_offset,
@@ -301,30 +1213,39 @@ async fn build_combined_svg(config: &Config, query: &CardQuery) -> Result<String
         query.lang_count(),
     );
 
-    Ok(card::render_card(
+    card::render_card(
         vec![rendered_stats, rendered_langs],
         x_offset,
         y_offset,
         gap,
         width,
-        "Stats",
+        &query.title(&title, card_stats.includes_private),
         &theme,
-    ))
+    )
 }
 
 #[get("/languages")]
-async fn languages_endpoint(config: Data<Config>, query: web::Query<CardQuery>) -> impl Responder {
-    match build_languages_svg(&config, &query).await {
-        Ok(svg) => svg_response(svg, config.cache_seconds),
+async fn languages_endpoint(
+    req: HttpRequest,
+    config: Data<SharedConfig>,
+    query: web::Query<CardQuery>,
+) -> impl Responder {
+    let config = config.current();
+    match fetch_stats(&config).await {
+        Ok(cached) => svg_response(
+            &req,
+            build_languages_svg(&cached.stats, &query),
+            cached.modified,
+            config.cache_seconds,
+        ),
         Err(err) => {
             error!("Failed to render /languages card: {err:#}");
-            error_svg_response("Failed to fetch GitHub languages", &query)
+            fetch_failure(&err, "Failed to fetch GitHub languages").svg_response(&query)
         }
     }
 }
 
-async fn build_languages_svg(config: &Config, query: &CardQuery) -> Result<String, anyhow::Error> {
-    let card_stats = fetch_stats(config).await?;
+fn build_languages_svg(card_stats: &github::Stats, query: &CardQuery) -> String {
     let theme = query.theme();
     let width = query.width();
     let x_offset = 25.0_f64;
@@ -339,39 +1260,221 @@ async fn build_languages_svg(config: &Config, query: &CardQuery) -> Result<Strin
         query.lang_count(),
     );
 
-    Ok(card::render_card(
+    card::render_card(
         vec![rendered_langs],
         x_offset,
         y_offset,
         gap,
         width,
-        "Top Languages",
+        &query.title("Top Languages", card_stats.includes_private),
         &theme,
-    ))
+    )
 }
 
-#[post("/refresh")]
-async fn refresh_endpoint(config: Data<Config>, req: actix_web::HttpRequest) -> impl Responder {
-    if config.refresh_token.is_empty() {
-        return HttpResponse::Forbidden()
-            .body("Refresh endpoint disabled: set STATS_REFRESH_TOKEN to enable");
+#[derive(Deserialize, Default)]
+struct BadgeQuery {
+    theme: Option<String>,
+    format: Option<String>,
+}
+
+impl BadgeQuery {
+    fn theme(&self) -> themes::Theme {
+        themes::from_name(self.theme.as_deref().unwrap_or("dark"))
+    }
+
+    fn is_svg(&self) -> bool {
+        self.format.as_deref() == Some("svg")
+    }
+}
+
+#[get("/badge/{metric}")]
+async fn badge_endpoint(
+    req: HttpRequest,
+    config: Data<SharedConfig>,
+    metric: web::Path<String>,
+    query: web::Query<BadgeQuery>,
+) -> Result<HttpResponse, Error> {
+    let config = config.current();
+    let Some(metric) = badge::Metric::from_name(&metric) else {
+        return Ok(HttpResponse::NotFound().body("Unknown badge metric"));
+    };
+    let theme = query.theme();
+    let (badge, last_modified, failure) = match fetch_stats(&config).await {
+        Ok(cached) => (
+            badge::Badge::for_metric(metric, &cached.stats, &theme),
+            Some(cached.modified),
+            None,
+        ),
+        Err(err) => {
+            error!("Failed to render /badge/{} badge: {err:#}", metric.label());
+            let failure = fetch_failure(&err, "Failed to fetch GitHub stats");
+            (
+                badge::Badge::error(metric, failure.badge_message, &theme),
+                None,
+                Some(failure),
+            )
+        }
+    };
+
+    if query.is_svg() {
+        let svg = badge::render_badge(&badge, &theme);
+        return Ok(match (last_modified, failure) {
+            (Some(last_modified), _) => {
+                svg_response(&req, svg, last_modified, config.cache_seconds)
+            }
+            (None, Some(failure)) => failure.response(mime::IMAGE_SVG, svg),
+            (None, None) => uncached_svg_response(svg),
+        });
     }
 
-    let provided = req
-        .headers()
+    // Shields only renders endpoint badges from 200 responses; errors are flagged in the body.
+    let endpoint =
+        badge::ShieldsEndpoint::new(badge, last_modified.is_none(), config.cache_seconds);
+    Ok(json_response(serde_json::to_string(&endpoint)?))
+}
+
+#[derive(Serialize)]
+struct RepositoryEntry<'a> {
+    name: &'a str,
+    #[serde(flatten)]
+    stats: &'a github::RepositoryStats,
+}
+
+/// Orders repositories by stars (descending), then by name for a stable listing. Private
+/// repositories are left out unless `include_private`.
+fn sorted_repositories(stats: &github::Stats, include_private: bool) -> Vec<RepositoryEntry<'_>> {
+    let mut entries: Vec<RepositoryEntry> = stats
+        .repositories
+        .iter()
+        .filter(|(_, stats)| include_private || !stats.private)
+        .map(|(name, stats)| RepositoryEntry { name, stats })
+        .collect();
+    entries.sort_by(|a, b| {
+        b.stats
+            .stars
+            .cmp(&a.stats.stars)
+            .then_with(|| a.name.cmp(b.name))
+    });
+    entries
+}
+
+/// Whether the request carries the refresh or metrics token, which unlocks private details.
+fn is_operator(req: &HttpRequest, config: &Config) -> bool {
+    let token = bearer_token(req);
+    [&config.refresh_token, &config.metrics_token]
+        .iter()
+        .any(|t| !t.is_empty() && token == t.as_str())
+}
+
+#[get("/repositories")]
+async fn repositories_endpoint(
+    config: Data<SharedConfig>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let config = config.current();
+    match fetch_stats(&config).await {
+        Ok(cached) => Ok(json_response(serde_json::to_string_pretty(
+            &sorted_repositories(&cached.stats, is_operator(&req, &config)),
+        )?)),
+        Err(err) => {
+            error!("Failed to list repositories: {err:#}");
+            let failure = fetch_failure(&err, "Failed to fetch GitHub stats");
+            Ok(failure.response(mime::TEXT_PLAIN_UTF_8, failure.message.clone()))
+        }
+    }
+}
+
+/// Marks the stats cache as stale without deleting it.
+///
+/// The file is kept so the next fetch can reuse per-repository data for repositories
+/// that have not been pushed to since.
+fn invalidate_cached_stats(path: &Path) -> io::Result<()> {
+    fs::metadata(path)?;
+    fs::File::create(stale_marker(path)).map(drop)
+}
+
+/// Per-window stats caches in `cache_path`, with their modification times.
+fn window_caches(cache_path: &str) -> Vec<(PathBuf, SystemTime)> {
+    let dir = if cache_path.is_empty() {
+        "."
+    } else {
+        cache_path
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with("stats_window_") && name.ends_with(".json")
+        })
+        .map(|entry| {
+            let modified = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .unwrap_or(UNIX_EPOCH);
+            (entry.path(), modified)
+        })
+        .collect()
+}
+
+/// Deletes every per-window stats cache; they hold no data worth keeping as a snapshot.
+fn remove_window_caches(cache_path: &str) {
+    for (path, _) in window_caches(cache_path) {
+        if let Err(err) = fs::remove_file(&path) {
+            debug!("Failed to remove window cache {}: {err}", path.display());
+        }
+    }
+}
+
+/// Deletes the least recently written window caches so that at most `keep` remain.
+fn prune_window_caches(cache_path: &str, keep: usize) {
+    let mut caches = window_caches(cache_path);
+    if caches.len() <= keep {
+        return;
+    }
+    caches.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+    for (path, _) in caches.drain(keep..) {
+        if let Err(err) = fs::remove_file(&path) {
+            debug!("Failed to remove window cache {}: {err}", path.display());
+        }
+    }
+}
+
+fn bearer_token(req: &HttpRequest) -> &str {
+    req.headers()
         .get(header::AUTHORIZATION)
         .and_then(|v| v.to_str().ok())
         .and_then(|v| v.strip_prefix("Bearer "))
-        .unwrap_or("");
+        .unwrap_or("")
+}
 
-    if provided != config.refresh_token {
-        return HttpResponse::Unauthorized()
-            .insert_header(("WWW-Authenticate", "Bearer"))
-            .finish();
+fn unauthorized() -> HttpResponse {
+    HttpResponse::Unauthorized()
+        .insert_header(("WWW-Authenticate", "Bearer"))
+        .finish()
+}
+
+#[post("/refresh")]
+async fn refresh_endpoint(
+    config: Data<SharedConfig>,
+    req: actix_web::HttpRequest,
+) -> impl Responder {
+    let config = config.current();
+    if config.refresh_token.is_empty() {
+        return HttpResponse::Forbidden()
+            .body("Refresh endpoint disabled: set STATS_REFRESH_TOKEN to enable");
     }
 
+    if bearer_token(&req) != config.refresh_token {
+        return unauthorized();
+    }
+
+    remove_window_caches(&config.cache_path);
     let path = stats_cache_path(&config.cache_path);
-    match fs::remove_file(&path) {
+    match invalidate_cached_stats(&path) {
         Ok(_) => {
             info!("Cache invalidated: {}", path.display());
             HttpResponse::Ok().body("Cache cleared")
@@ -386,10 +1489,80 @@ async fn refresh_endpoint(config: Data<Config>, req: actix_web::HttpRequest) ->
     }
 }
 
+/// Receives GitHub webhook deliveries and refreshes the stats once events stop arriving.
+async fn webhook_endpoint(
+    config: Data<SharedConfig>,
+    req: HttpRequest,
+    payload: web::Bytes,
+) -> HttpResponse {
+    let current = config.current();
+    if current.webhook_secret.is_empty() {
+        return HttpResponse::Forbidden()
+            .body("Webhook endpoint disabled: set STATS_WEBHOOK_SECRET to enable");
+    }
+    let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok());
+    if !webhook::signature_valid(
+        &current.webhook_secret,
+        &payload,
+        header("X-Hub-Signature-256"),
+    ) {
+        return HttpResponse::Unauthorized().body("Invalid signature");
+    }
+
+    let event = header("X-GitHub-Event").unwrap_or_default();
+    if event == "ping" {
+        return HttpResponse::Ok().body("pong");
+    }
+    if !webhook::triggers_refresh(event) {
+        return HttpResponse::Ok().body(format!("Ignoring {event} event"));
+    }
+    match webhook::concerns_user(&payload, &current.github_user) {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::Ok().body(format!(
+                "Ignoring {event} event not involving {}",
+                current.github_user
+            ))
+        }
+        Err(err) => return HttpResponse::BadRequest().body(format!("Invalid payload: {err}")),
+    }
+
+    let delay = current.webhook_debounce_seconds;
+    if webhook::schedule_refresh(config, Duration::from_secs(delay.into())) {
+        info!("Received {event} event from GitHub, refreshing stats in {delay}s");
+        HttpResponse::Accepted().body(format!("Refresh scheduled in {delay}s"))
+    } else {
+        debug!("Received {event} event from GitHub, refresh already scheduled");
+        HttpResponse::Accepted().body("Refresh already scheduled")
+    }
+}
+
 #[derive(Serialize)]
 struct HealthResponse {
     status: &'static str,
+    /// GitHub accounts whose stats are served.
+    users: Vec<String>,
     cache: CacheStatus,
+    last_refresh: Option<health::RefreshOutcome>,
+    /// Unix timestamp of the last refresh that succeeded.
+    last_successful_refresh: Option<u64>,
+    credentials: Vec<CredentialStatus>,
+    /// Last rate-limit budget GitHub reported per credential and resource.
+    rate_limits: Vec<rate_limit::BudgetStatus>,
+}
+
+#[derive(Serialize)]
+struct CredentialStatus {
+    credential: String,
+    /// `valid` or `invalid` as GitHub last answered, `unknown` before the first request.
+    status: &'static str,
+}
+
+#[derive(Serialize)]
+struct ProbeResponse {
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<&'static str>,
 }
 
 #[derive(Serialize)]
@@ -414,7 +1587,7 @@ fn cache_status(path: &Path, max_age_secs: u32) -> CacheStatus {
                 return CacheStatus {
                     exists: true,
                     age_seconds: Some(age),
-                    fresh: age < max_age_secs as u64,
+                    fresh: age < max_age_secs as u64 && !stale_marker(path).exists(),
                 };
             }
         }
@@ -427,24 +1600,118 @@ fn cache_status(path: &Path, max_age_secs: u32) -> CacheStatus {
 }
 
 #[get("/health")]
-async fn health_endpoint(config: Data<Config>) -> impl Responder {
+async fn health_endpoint(config: Data<SharedConfig>) -> impl Responder {
+    let config = config.current();
     let cache = cache_status(&stats_cache_path(&config.cache_path), config.cache_seconds);
     let (http_status, status_text) = if cache.fresh {
         (StatusCode::OK, "healthy")
     } else {
         (StatusCode::SERVICE_UNAVAILABLE, "degraded")
     };
+    let credentials = config.credentials();
+    let credentials = credentials
+        .keys()
+        .iter()
+        .zip(credentials.labels())
+        .map(|(key, credential)| CredentialStatus {
+            status: health::credential_status(key),
+            credential,
+        })
+        .collect();
     let health = HealthResponse {
         status: status_text,
+        users: vec![config.github_user.clone()],
         cache,
+        last_refresh: health::last_refresh(),
+        last_successful_refresh: health::last_success(),
+        credentials,
+        rate_limits: rate_limit::status(),
     };
     let json = serde_json::to_string(&health).expect("HealthResponse is always serializable");
-    HttpResponse::build(http_status)
+    health_response(http_status, json)
+}
+
+fn health_response(status: StatusCode, json: String) -> HttpResponse {
+    HttpResponse::build(status)
         .insert_header(header::ContentType(mime::APPLICATION_JSON))
         .insert_header(("X-Content-Type-Options", "nosniff"))
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
         .body(json)
 }
 
+fn probe_response(status: StatusCode, body: ProbeResponse) -> HttpResponse {
+    let json = serde_json::to_string(&body).expect("ProbeResponse is always serializable");
+    health_response(status, json)
+}
+
+/// Liveness: the process is up and answering requests.
+#[get("/health/live")]
+async fn health_live_endpoint() -> impl Responder {
+    probe_response(
+        StatusCode::OK,
+        ProbeResponse {
+            status: "alive",
+            reason: None,
+        },
+    )
+}
+
+/// Whether cards can be served: from fresh cached stats, or by the crawl a card request
+/// triggers when the cache is stale or missing, unless the last crawl failed.
+fn readiness(
+    cache: &CacheStatus,
+    last_refresh: Option<&health::RefreshOutcome>,
+) -> Result<(), &'static str> {
+    // Only requests start crawls, so before the first one a stale or missing cache has to
+    // count as ready, or no request would ever arrive to refresh it.
+    let last_failed = last_refresh.is_some_and(|refresh| !refresh.succeeded);
+    if cache.fresh || !last_failed {
+        return Ok(());
+    }
+    if cache.exists {
+        Err("cached stats are stale and the last refresh failed")
+    } else {
+        Err("no cached stats and the last refresh failed")
+    }
+}
+
+/// Readiness: unlike `/health`, a stale or missing cache counts as ready unless the last
+/// crawl failed.
+#[get("/health/ready")]
+async fn health_ready_endpoint(config: Data<SharedConfig>) -> impl Responder {
+    let config = config.current();
+    let cache = cache_status(&stats_cache_path(&config.cache_path), config.cache_seconds);
+    match readiness(&cache, health::last_refresh().as_ref()) {
+        Ok(()) => probe_response(
+            StatusCode::OK,
+            ProbeResponse {
+                status: "ready",
+                reason: None,
+            },
+        ),
+        Err(reason) => probe_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            ProbeResponse {
+                status: "not ready",
+                reason: Some(reason),
+            },
+        ),
+    }
+}
+
+#[get("/metrics")]
+async fn metrics_endpoint(config: Data<SharedConfig>, req: HttpRequest) -> impl Responder {
+    let config = config.current();
+    if !config.metrics_token.is_empty() && bearer_token(&req) != config.metrics_token {
+        return unauthorized();
+    }
+    let cache = cache_status(&stats_cache_path(&config.cache_path), config.cache_seconds);
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .body(metrics::render(cache.age_seconds))
+}
+
 #[get("/favicon.ico")]
 async fn favicon_endpoint() -> Result<impl Responder, Error> {
     NamedFile::open("static/favicon.ico")
@@ -476,54 +1743,140 @@ fn load_dotenv() {
     }
 }
 
+fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(root_endpoint)
+        .service(stats_endpoint)
+        .service(languages_endpoint)
+        .service(combined_endpoint)
+        .service(badge_endpoint)
+        .service(repositories_endpoint)
+        .service(refresh_endpoint)
+        .service(
+            web::resource("/webhook/github")
+                .app_data(web::PayloadConfig::new(WEBHOOK_MAX_PAYLOAD))
+                .route(web::post().to(webhook_endpoint)),
+        )
+        .service(health_endpoint)
+        .service(health_live_endpoint)
+        .service(health_ready_endpoint)
+        .service(metrics_endpoint)
+        .service(favicon_endpoint);
+}
+
 fn main() -> Result<(), Error> {
     load_dotenv();
-    actix_web::rt::System::new().block_on(run())
+    let cli = cli::Cli::parse();
+    logging::init(
+        cli.log_format(),
+        if cli.logs_to_stdout() {
+            Target::Stdout
+        } else {
+            Target::Stderr
+        },
+    );
+    actix_web::rt::System::new().block_on(cli.run())
 }
 
-async fn run() -> Result<(), Error> {
-    env_logger::builder()
-        .filter_level(LevelFilter::Info)
-        .parse_default_env()
-        .target(Target::Stdout)
-        .init();
+/// Gives each request an id, which its log lines and those of the GitHub calls it triggers
+/// carry, then logs the request once handled and records its metrics.
+fn observe_request<S, B>(
+    req: ServiceRequest,
+    srv: &S,
+) -> impl Future<Output = Result<ServiceResponse<B>, actix_web::Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+{
+    let started = Instant::now();
+    let request_id = logging::request_id_from(
+        req.headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok()),
+    );
+    // The route pattern rather than the path, so unknown paths share one label.
+    let endpoint = req
+        .match_pattern()
+        .unwrap_or_else(|| String::from("unmatched"));
+    let method = req.method().to_string();
+    let path = req.path().to_string();
+    let query = req.query_string().to_string();
+    let peer = req.peer_addr().map_or_else(
+        || String::from("-"),
+        |addr| {
+            let trusted = req
+                .app_data::<Data<SharedConfig>>()
+                .map(|config| config.current().trusted_proxies.clone())
+                .unwrap_or_default();
+            client_ip::client_ip(addr.ip(), req.headers(), &trusted).to_string()
+        },
+    );
+    let response = srv.call(req);
 
-    let config = match Config::from_env() {
-        Ok(config) => config,
-        Err(err) => {
-            error!("Failed to read config: {:?}", err);
-            return Err(Error::other(err.to_string()));
+    logging::in_request(request_id.clone(), async move {
+        let mut response = response.await;
+        let status = match &response {
+            Ok(response) => response.status(),
+            Err(err) => err.as_response_error().status_code(),
+        };
+        let duration = started.elapsed();
+        metrics::http_request(&endpoint, status.as_u16(), duration);
+        info!(
+            target: "github_stats::access",
+            method = method.as_str(),
+            path = path.as_str(),
+            endpoint = endpoint.as_str(),
+            query = query.as_str(),
+            status = status.as_u16(),
+            duration_ms = duration.as_millis() as u64,
+            cache = logging::cache_status().unwrap_or("none"),
+            peer = peer.as_str();
+            "{method} {path} {}", status.as_u16()
+        );
+        if let (Ok(response), Ok(id)) = (&mut response, HeaderValue::from_str(&request_id)) {
+            response.headers_mut().insert(REQUEST_ID_HEADER, id);
         }
-    };
+        response
+    })
+}
 
+/// Runs the HTTP server, reloading the configuration from `config_file` and on SIGHUP.
+async fn serve(config: Config, config_file: Option<PathBuf>) -> Result<(), Error> {
     let ipv4_address = config.ipv4_address.clone();
     let ipv6_address = config.ipv6_address.clone();
     let port = config.port;
 
-    let governor_conf = match GovernorConfigBuilder::default()
-        .seconds_per_request(3)
-        .burst_size(3)
-        .finish()
-    {
-        Some(conf) => conf,
-        None => {
-            error!("Failed to build Governor config");
-            return Err(Error::other("Failed to build Governor config"));
-        }
-    };
+    let cards_limit = Limiter::new(
+        Bucket::Cards,
+        config.rate_limit_per_minute,
+        config.rate_limit_burst,
+    );
+    let refresh_limit = Limiter::new(
+        Bucket::Refresh,
+        config.rate_limit_refresh_per_minute,
+        config.rate_limit_refresh_burst,
+    );
+    let metrics_limit = Limiter::new(
+        Bucket::Metrics,
+        config.rate_limit_metrics_per_minute,
+        config.rate_limit_metrics_burst,
+    );
+    let webhook_limit = Limiter::new(
+        Bucket::Webhook,
+        config.rate_limit_webhook_per_minute,
+        config.rate_limit_webhook_burst,
+    );
+
+    let shared_config = Data::new(SharedConfig::new(config));
+    actix_web::rt::spawn(reload::watch(shared_config.clone(), config_file));
 
     let mut server = HttpServer::new(move || {
         App::new()
-            .wrap(Logger::default())
-            .wrap(Governor::new(&governor_conf))
-            .app_data(Data::new(config.clone()))
-            .service(root_endpoint)
-            .service(stats_endpoint)
-            .service(languages_endpoint)
-            .service(combined_endpoint)
-            .service(refresh_endpoint)
-            .service(health_endpoint)
-            .service(favicon_endpoint)
+            .wrap(cards_limit.middleware())
+            .wrap(refresh_limit.middleware())
+            .wrap(metrics_limit.middleware())
+            .wrap(webhook_limit.middleware())
+            .wrap_fn(observe_request)
+            .app_data(shared_config.clone())
+            .configure(routes)
             .default_service(web::to(default_handler))
     });
 
@@ -564,3 +1917,805 @@ async fn run() -> Result<(), Error> {
 
     server.workers(2).run().await
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use hmac::{Hmac, Mac};
+
+    use super::*;
+    use mock_github::{MockGithub, MockResponse};
+    use test_dir::TestDir;
+
+    fn etag() -> EntityTag {
+        body_etag("<svg/>")
+    }
+
+    #[test]
+    fn body_etag_is_strong_and_stable() {
+        let tag = etag();
+        assert!(!tag.weak);
+        assert_eq!(tag, body_etag("<svg/>"));
+        assert_ne!(tag, body_etag("<svg></svg>"));
+    }
+
+    #[test]
+    fn not_modified_without_validators() {
+        let req = TestRequest::default().to_http_request();
+        assert!(!is_not_modified(&req, &etag(), SystemTime::now()));
+    }
+
+    #[test]
+    fn not_modified_matching_etag() {
+        let req = TestRequest::default()
+            .insert_header(header::IfNoneMatch::Items(vec![etag()]))
+            .to_http_request();
+        assert!(is_not_modified(&req, &etag(), SystemTime::now()));
+    }
+
+    #[test]
+    fn not_modified_weak_etag_matches() {
+        let weak = EntityTag::new_weak(etag().tag().to_string());
+        let req = TestRequest::default()
+            .insert_header(header::IfNoneMatch::Items(vec![weak]))
+            .to_http_request();
+        assert!(is_not_modified(&req, &etag(), SystemTime::now()));
+    }
+
+    #[test]
+    fn modified_when_etag_differs() {
+        let req = TestRequest::default()
+            .insert_header(header::IfNoneMatch::Items(vec![body_etag("other")]))
+            .to_http_request();
+        assert!(!is_not_modified(&req, &etag(), SystemTime::now()));
+    }
+
+    #[test]
+    fn not_modified_since_last_modified() {
+        let last_modified = SystemTime::now() - Duration::from_secs(60);
+        let req = TestRequest::default()
+            .insert_header(header::IfModifiedSince(SystemTime::now().into()))
+            .to_http_request();
+        assert!(is_not_modified(&req, &etag(), last_modified));
+    }
+
+    #[test]
+    fn modified_after_if_modified_since() {
+        let since = SystemTime::now() - Duration::from_secs(60);
+        let req = TestRequest::default()
+            .insert_header(header::IfModifiedSince(since.into()))
+            .to_http_request();
+        assert!(!is_not_modified(&req, &etag(), SystemTime::now()));
+    }
+
+    #[actix_web::test]
+    async fn config_reload_makes_cards_modified() {
+        let mock = MockGithub::start().await;
+        let _shared = SharedConfig::new(mock_config(&mock, "loaded-at", "test-token-loaded-at"));
+        let since = SystemTime::now() - Duration::from_secs(60);
+        let req = TestRequest::default()
+            .insert_header(header::IfModifiedSince(since.into()))
+            .to_http_request();
+        let cached = since - Duration::from_secs(60);
+        let res = svg_response(&req, String::from("<svg/>"), cached, ONE_DAY);
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn if_none_match_takes_precedence_over_if_modified_since() {
+        let req = TestRequest::default()
+            .insert_header(header::IfNoneMatch::Items(vec![body_etag("other")]))
+            .insert_header(header::IfModifiedSince(SystemTime::now().into()))
+            .to_http_request();
+        assert!(!is_not_modified(
+            &req,
+            &etag(),
+            SystemTime::now() - Duration::from_secs(60)
+        ));
+    }
+
+    #[test]
+    fn invalidated_cache_is_stale_but_kept_as_snapshot() {
+        let dir = TestDir::new("invalidated-cache");
+        let path = stats_cache_path(dir.path().to_str().unwrap());
+        let stats: github::Stats =
+            serde_json::from_str(r#"{"total_stars":1,"total_commits":2,"languages":{}}"#).unwrap();
+        write_cached_stats(&path, &stats).unwrap();
+        assert!(read_cached_stats::<github::Stats>(&path, ONE_DAY)
+            .unwrap()
+            .is_some());
+
+        invalidate_cached_stats(&path).unwrap();
+        assert!(read_cached_stats::<github::Stats>(&path, ONE_DAY)
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            read_stats_snapshot::<github::Stats>(&path)
+                .unwrap()
+                .total_stars,
+            1
+        );
+        // The file keeps its age; only freshness is lost.
+        let status = cache_status(&path, ONE_DAY);
+        assert!(!status.fresh);
+        assert!(status.age_seconds.unwrap() < 60);
+
+        write_cached_stats(&path, &stats).unwrap();
+        assert!(read_cached_stats::<github::Stats>(&path, ONE_DAY)
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn sorted_repositories_orders_by_stars_then_name() {
+        let json = r#"{"total_stars":0,"total_commits":0,"languages":{},"repositories":{
+            "u/b":{"stars":5},"u/a":{"stars":5},"u/c":{"stars":9}}}"#;
+        let stats: github::Stats = serde_json::from_str(json).unwrap();
+        let names: Vec<&str> = sorted_repositories(&stats, false)
+            .iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(names, ["u/c", "u/a", "u/b"]);
+    }
+
+    #[actix_web::test]
+    async fn repositories_endpoint_hides_private_repositories() {
+        let mock = MockGithub::start().await;
+        let mut config = mock_config(&mock, "private-repos", "test-token-private-repos");
+        config.refresh_token = String::from("operator");
+        fs::write(
+            stats_cache_path(&config.cache_path),
+            r#"{"total_stars":0,"total_commits":0,"languages":{},"includes_private":true,
+            "repositories":{"octo/public":{"stars":1},"octo/secret":{"stars":2,"private":true}}}"#,
+        )
+        .unwrap();
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(SharedConfig::new(config)))
+                .configure(routes),
+        )
+        .await;
+        let names = |body: web::Bytes| -> Vec<String> {
+            let list: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+            list.iter()
+                .map(|r| r["name"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        let res = call_service(&app, TestRequest::get().uri("/repositories").to_request()).await;
+        assert_eq!(names(read_body(res).await), ["octo/public"]);
+
+        let req = TestRequest::get()
+            .uri("/repositories")
+            .insert_header((header::AUTHORIZATION, "Bearer operator"))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(names(read_body(res).await), ["octo/secret", "octo/public"]);
+    }
+
+    #[test]
+    fn card_title_private_marker() {
+        let mut stats: github::Stats =
+            serde_json::from_str(r#"{"total_stars":0,"total_commits":0,"languages":{}}"#).unwrap();
+        let query = CardQuery {
+            private_marker: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(query.title("Stats", stats.includes_private), "Stats");
+        stats.includes_private = true;
+        assert_eq!(
+            query.title("Stats", stats.includes_private),
+            "Stats (incl. private)"
+        );
+        assert_eq!(
+            CardQuery::default().title("Stats", stats.includes_private),
+            "Stats"
+        );
+    }
+
+    fn window_query(year: Option<i32>, since: Option<&str>, until: Option<&str>) -> CardQuery {
+        CardQuery {
+            year,
+            since: since.map(str::to_string),
+            until: until.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn card_query_without_window() {
+        assert_eq!(CardQuery::default().window(), Ok(None));
+    }
+
+    #[test]
+    fn card_query_year_window() {
+        let window = window_query(Some(2024), None, None)
+            .window()
+            .unwrap()
+            .unwrap();
+        assert_eq!(window.since.to_string(), "2024-01-01");
+        assert_eq!(window.until.to_string(), "2024-12-31");
+        assert_eq!(window_label(&window), "2024");
+    }
+
+    #[test]
+    fn card_query_since_until_window() {
+        let window = window_query(None, Some("2024-03-01"), Some("2024-06-30"))
+            .window()
+            .unwrap()
+            .unwrap();
+        assert_eq!(window_label(&window), "2024-03-01 – 2024-06-30");
+    }
+
+    #[test]
+    fn card_query_since_defaults_until_to_today() {
+        let window = window_query(None, Some("2024-03-01"), None)
+            .window()
+            .unwrap()
+            .unwrap();
+        assert_eq!(window.until, date::Date::today());
+    }
+
+    #[test]
+    fn card_query_window_clamps_to_earliest_year() {
+        let window = window_query(None, Some("1999-01-01"), Some("2010-01-01"))
+            .window()
+            .unwrap()
+            .unwrap();
+        assert_eq!(window.since.to_string(), "2008-01-01");
+    }
+
+    #[test]
+    fn card_query_invalid_windows() {
+        assert!(window_query(Some(2024), Some("2024-01-01"), None)
+            .window()
+            .is_err());
+        assert!(window_query(None, None, Some("2024-01-01"))
+            .window()
+            .is_err());
+        assert!(window_query(None, Some("2024-13-01"), None)
+            .window()
+            .is_err());
+        assert!(window_query(None, Some("2024-06-01"), Some("2024-01-01"))
+            .window()
+            .is_err());
+        assert!(window_query(Some(1990), None, None).window().is_err());
+    }
+
+    #[test]
+    fn card_query_windows_are_bounded() {
+        let tomorrow = date::Date::from_unix_days(date::Date::today().unix_days() + 1).to_string();
+        assert!(window_query(None, Some(&tomorrow), None).window().is_err());
+        assert!(window_query(None, Some("2020-01-01"), Some(&tomorrow))
+            .window()
+            .is_err());
+        assert!(window_query(None, Some("2008-01-01"), Some("9999-12-31"))
+            .window()
+            .is_err());
+        assert!(window_query(None, Some("2010-01-01"), Some("2020-12-31"))
+            .window()
+            .is_err());
+        assert!(window_query(None, Some("2011-01-01"), Some("2020-12-31"))
+            .window()
+            .is_ok());
+    }
+
+    #[test]
+    fn window_caches_are_pruned_oldest_first() {
+        let dir = TestDir::new("window-pruning");
+        let dir = dir.path();
+        let cache_path = dir.to_str().unwrap();
+        for (i, name) in ["a", "b", "c"].iter().enumerate() {
+            let path = dir.join(format!("stats_window_{name}.json"));
+            fs::write(&path, "{}").unwrap();
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(UNIX_EPOCH + Duration::from_secs(1000 + i as u64))
+                .unwrap();
+        }
+        fs::write(dir.join(STATS_CACHE_JSON), "{}").unwrap();
+
+        prune_window_caches(cache_path, 2);
+        let mut left: Vec<PathBuf> = window_caches(cache_path)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        left.sort();
+        assert_eq!(
+            left,
+            [
+                dir.join("stats_window_b.json"),
+                dir.join("stats_window_c.json")
+            ]
+        );
+        assert!(dir.join(STATS_CACHE_JSON).exists());
+    }
+
+    #[test]
+    fn stale_cache_is_ready_until_a_refresh_fails() {
+        let cache = |exists| CacheStatus {
+            exists,
+            age_seconds: None,
+            fresh: false,
+        };
+        let fresh = CacheStatus {
+            exists: true,
+            age_seconds: Some(0),
+            fresh: true,
+        };
+        let refresh = |succeeded| health::RefreshOutcome {
+            finished_at: 0,
+            duration_ms: 0,
+            succeeded,
+            error: None,
+        };
+        assert!(readiness(&fresh, Some(&refresh(false))).is_ok());
+        assert!(readiness(&cache(true), Some(&refresh(true))).is_ok());
+        assert_eq!(
+            readiness(&cache(true), Some(&refresh(false))),
+            Err("cached stats are stale and the last refresh failed")
+        );
+        // Stale and never refreshed: the next card request crawls.
+        assert!(readiness(&cache(true), None).is_ok());
+        assert!(readiness(&cache(false), None).is_ok());
+        assert!(readiness(&cache(false), Some(&refresh(true))).is_ok());
+        assert!(readiness(&cache(false), Some(&refresh(false))).is_err());
+    }
+
+    #[test]
+    fn invalidate_missing_cache_is_not_found() {
+        let err = invalidate_cached_stats(Path::new("/nonexistent/stats_cache.json")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn fetch_failure_rate_limited_sets_retry_after() {
+        let until = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 600;
+        let err = anyhow::Error::new(github::GithubError::RateLimited { until: Some(until) });
+        let failure = fetch_failure(&err, "fallback");
+        assert_eq!(failure.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(failure.retry_after.is_some_and(|secs| secs <= 600));
+        assert!(failure.message.starts_with("GitHub rate limit reached"));
+        assert_eq!(failure.badge_message, "rate limited");
+    }
+
+    #[test]
+    fn fetch_failure_maps_github_errors_to_status() {
+        let not_found = anyhow::Error::new(github::GithubError::UserNotFound("octo".to_string()));
+        assert_eq!(
+            fetch_failure(&not_found, "fallback").status,
+            StatusCode::NOT_FOUND
+        );
+        let upstream = anyhow::Error::new(github::GithubError::Upstream(
+            reqwest::StatusCode::BAD_GATEWAY,
+        ));
+        assert_eq!(
+            fetch_failure(&upstream, "fallback").status,
+            StatusCode::BAD_GATEWAY
+        );
+    }
+
+    #[test]
+    fn fetch_failure_other_errors_use_fallback() {
+        let err = anyhow::anyhow!("disk full");
+        let failure = fetch_failure(&err, "Failed to fetch GitHub stats");
+        assert_eq!(failure.status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(failure.message, "Failed to fetch GitHub stats");
+        assert!(failure.retry_after.is_none());
+    }
+
+    /// Config pointing at `mock`, with its own cache directory so tests don't share stats.
+    fn mock_config(mock: &MockGithub, name: &str, token: &str) -> Config {
+        let cache_path =
+            std::env::temp_dir().join(format!("github-stats-test-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&cache_path);
+        fs::create_dir_all(&cache_path).unwrap();
+        Config::for_tests(&[
+            ("github_token", token),
+            ("github_api_url", mock.url()),
+            ("cache_path", cache_path.to_str().unwrap()),
+        ])
+    }
+
+    fn script_account(mock: &MockGithub) {
+        mock.on(
+            "/search/repositories?q=user:octo&per_page=100",
+            [MockResponse::json(serde_json::json!({
+                "total_count": 1,
+                "incomplete_results": false,
+                "items": [{
+                    "full_name": "octo/hello",
+                    "stargazers_count": 7,
+                    "forks_count": 2,
+                    "fork": false,
+                    "archived": false,
+                    "languages_url": format!("{}/repos/octo/hello/languages", mock.url()),
+                    "pushed_at": "2024-01-01T00:00:00Z",
+                }],
+            }))],
+        );
+        mock.on(
+            "/repos/octo/hello/languages",
+            [MockResponse::json(serde_json::json!({"Rust": 1000}))],
+        );
+        mock.on(
+            "/search/commits?q=author:octo",
+            [MockResponse::json(serde_json::json!({"total_count": 123}))],
+        );
+    }
+
+    #[actix_web::test]
+    async fn crawl_replaces_cached_stats_only_on_success() {
+        let mock = MockGithub::start().await;
+        let config = mock_config(&mock, "crawl", "test-token-crawl");
+        let path = stats_cache_path(&config.cache_path);
+        let stats: github::Stats =
+            serde_json::from_str(r#"{"total_stars":1,"total_commits":2,"languages":{}}"#).unwrap();
+        write_cached_stats(&path, &stats).unwrap();
+
+        // Nothing is scripted yet, so the crawl fails and the fresh cache stays.
+        assert!(crawl_stats(&config).await.is_err());
+        let cached = read_cached_stats::<github::Stats>(&path, ONE_DAY)
+            .unwrap()
+            .unwrap();
+        assert_eq!(cached.stats.total_stars, 1);
+
+        script_account(&mock);
+        assert_eq!(crawl_stats(&config).await.unwrap().stats.total_stars, 7);
+        let cached = read_cached_stats::<github::Stats>(&path, ONE_DAY)
+            .unwrap()
+            .unwrap();
+        assert_eq!(cached.stats.total_stars, 7);
+    }
+
+    #[actix_web::test]
+    async fn incomplete_repository_listing_is_not_cached() {
+        let mock = MockGithub::start().await;
+        script_account(&mock);
+        mock.on(
+            "/search/repositories?q=user:octo&per_page=100",
+            [MockResponse::json(serde_json::json!({
+                "total_count": 2,
+                "incomplete_results": true,
+                "items": [],
+            }))],
+        );
+        let config = mock_config(&mock, "incomplete", "test-token-incomplete-crawl");
+        let err = crawl_stats(&config).await.err().unwrap();
+        assert!(matches!(
+            err.downcast_ref::<github::GithubError>(),
+            Some(github::GithubError::Incomplete(_))
+        ));
+        assert!(!stats_cache_path(&config.cache_path).exists());
+    }
+
+    #[actix_web::test]
+    async fn stats_and_badge_endpoints_render_mocked_account() {
+        let mock = MockGithub::start().await;
+        script_account(&mock);
+        let config = mock_config(&mock, "render", "test-token-render");
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(SharedConfig::new(config)))
+                .configure(routes),
+        )
+        .await;
+
+        let res = call_service(&app, TestRequest::get().uri("/stats").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let svg = String::from_utf8(read_body(res).await.to_vec()).unwrap();
+        assert!(svg.contains("123"));
+
+        let res = call_service(&app, TestRequest::get().uri("/badge/stars").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let badge: serde_json::Value = serde_json::from_slice(&read_body(res).await).unwrap();
+        assert_eq!(badge["message"], "7");
+        assert_eq!(badge["isError"], false);
+
+        // The second request is served from the stats cache.
+        assert_eq!(mock.hits("/search/commits?q=author:octo"), 1);
+    }
+
+    #[actix_web::test]
+    async fn repositories_endpoint_reports_bad_credentials() {
+        let mock = MockGithub::start().await;
+        mock.on(
+            "/search/repositories?q=user:octo&per_page=100",
+            [MockResponse::status(401)],
+        );
+        let config = mock_config(&mock, "unauthorized", "test-token-unauthorized");
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(SharedConfig::new(config)))
+                .configure(routes),
+        )
+        .await;
+
+        let res = call_service(&app, TestRequest::get().uri("/repositories").to_request()).await;
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[actix_web::test]
+    async fn metrics_endpoint_requires_configured_token() {
+        let mock = MockGithub::start().await;
+        script_account(&mock);
+        let mut config = mock_config(&mock, "metrics", "test-token-metrics");
+        config.metrics_token = String::from("scrape");
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(SharedConfig::new(config)))
+                .configure(routes),
+        )
+        .await;
+        call_service(&app, TestRequest::get().uri("/stats").to_request()).await;
+
+        let res = call_service(&app, TestRequest::get().uri("/metrics").to_request()).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let req = TestRequest::get()
+            .uri("/metrics")
+            .insert_header((header::AUTHORIZATION, "Bearer scrape"))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let text = String::from_utf8(read_body(res).await.to_vec()).unwrap();
+        assert!(text.contains(r#"github_stats_github_requests_total{status="200"}"#));
+        assert!(text.contains(
+            r#"github_stats_crawl_duration_seconds_count{kind="stats",result="success"}"#
+        ));
+        assert!(text.contains("github_stats_cache_age_seconds "));
+        assert!(text.contains("github_stats_last_successful_refresh_timestamp_seconds "));
+    }
+
+    #[actix_web::test]
+    async fn responses_carry_the_request_id() {
+        let mock = MockGithub::start().await;
+        let config = mock_config(&mock, "request-id", "test-token-request-id");
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(SharedConfig::new(config)))
+                .wrap_fn(observe_request)
+                .configure(routes),
+        )
+        .await;
+
+        let req = TestRequest::get()
+            .uri("/health")
+            .insert_header((REQUEST_ID_HEADER, "from-proxy.1"))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(
+            res.headers().get(REQUEST_ID_HEADER).unwrap(),
+            "from-proxy.1"
+        );
+
+        let res = call_service(&app, TestRequest::get().uri("/health").to_request()).await;
+        assert_eq!(res.headers().get(REQUEST_ID_HEADER).unwrap().len(), 16);
+    }
+
+    #[actix_web::test]
+    async fn rate_limit_keys_on_the_forwarded_client() {
+        let mock = MockGithub::start().await;
+        let mut config = mock_config(&mock, "rate-limit", "test-token-rate-limit");
+        config.trusted_proxies = vec!["10.0.0.0/8".parse().unwrap()];
+        config.rate_limit_exempt = vec!["203.0.113.5".parse().unwrap()];
+        let limit = Limiter::new(Bucket::Cards, 60, 1);
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(SharedConfig::new(config)))
+                .wrap(limit.middleware())
+                .configure(routes),
+        )
+        .await;
+        let from = |client: &str| {
+            TestRequest::get()
+                .uri("/health/live")
+                .peer_addr("10.0.0.1:4711".parse().unwrap())
+                .insert_header(("X-Forwarded-For", client.to_string()))
+                .to_request()
+        };
+
+        assert_eq!(
+            call_service(&app, from("203.0.113.9")).await.status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            call_service(&app, from("203.0.113.9")).await.status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        // Behind the same proxy, another client has its own budget.
+        assert_eq!(
+            call_service(&app, from("198.51.100.2")).await.status(),
+            StatusCode::OK
+        );
+        for _ in 0..3 {
+            assert_eq!(
+                call_service(&app, from("203.0.113.5")).await.status(),
+                StatusCode::OK
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn webhook_schedules_one_refresh_for_signed_events() {
+        let mock = MockGithub::start().await;
+        let mut config = mock_config(&mock, "webhook", "test-token-webhook");
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(SharedConfig::new(config.clone())))
+                .configure(routes),
+        )
+        .await;
+        let delivery = |event: &str, payload: &str, secret: &str| {
+            let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+            mac.update(payload.as_bytes());
+            let signature = mac
+                .finalize()
+                .into_bytes()
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect::<String>();
+            TestRequest::post()
+                .uri("/webhook/github")
+                .insert_header(("X-GitHub-Event", event.to_string()))
+                .insert_header(("X-Hub-Signature-256", format!("sha256={signature}")))
+                .set_payload(payload.to_string())
+                .to_request()
+        };
+        let push = r#"{"repository":{"owner":{"login":"octo"}},"sender":{"login":"octo"}}"#;
+
+        let res = call_service(&app, delivery("push", push, "hook-secret")).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        config.webhook_secret = String::from("hook-secret");
+        config.webhook_debounce_seconds = 3600;
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(SharedConfig::new(config)))
+                .configure(routes),
+        )
+        .await;
+
+        let res = call_service(&app, delivery("push", push, "wrong-secret")).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let oversized = " ".repeat(WEBHOOK_MAX_PAYLOAD + 1);
+        let res = call_service(&app, delivery("push", &oversized, "hook-secret")).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let res = call_service(&app, delivery("ping", "{}", "hook-secret")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = call_service(&app, delivery("issues", push, "hook-secret")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let other = r#"{"repository":{"owner":{"login":"acme"}},"sender":{"login":"fan"}}"#;
+        let res = call_service(&app, delivery("star", other, "hook-secret")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = call_service(&app, delivery("push", push, "hook-secret")).await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert_eq!(read_body(res).await, "Refresh scheduled in 3600s");
+        let res = call_service(&app, delivery("star", push, "hook-secret")).await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        assert_eq!(read_body(res).await, "Refresh already scheduled");
+    }
+
+    #[actix_web::test]
+    async fn health_endpoints_report_details_and_probes() {
+        let mock = MockGithub::start().await;
+        let config = mock_config(&mock, "health", "test-token-health");
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(SharedConfig::new(config)))
+                .configure(routes),
+        )
+        .await;
+
+        let res = call_service(&app, TestRequest::get().uri("/health/live").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // No cache yet: degraded, but probes should not restart the process for it.
+        let res = call_service(&app, TestRequest::get().uri("/health").to_request()).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let health: serde_json::Value = serde_json::from_slice(&read_body(res).await).unwrap();
+        assert_eq!(health["status"], "degraded");
+        assert_eq!(health["users"], serde_json::json!(["octo"]));
+        assert_eq!(
+            health["credentials"],
+            serde_json::json!([{"credential": "…alth", "status": "unknown"}])
+        );
+    }
+
+    /// Loads `contents` as a config file with the given extension, overridden by `env`.
+    fn load_file(extension: &str, contents: &str, env: &[(&str, &str)]) -> Result<Config, String> {
+        static FILES: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "github-stats-config-{}-{}.{extension}",
+            std::process::id(),
+            FILES.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+        ));
+        fs::write(&path, contents).unwrap();
+        let env = env
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let result = Config::with_file(Config::defaults().unwrap(), &path)
+            .and_then(|builder| {
+                builder
+                    .add_source(Config::environment().source(Some(env)))
+                    .build()
+            })
+            .and_then(|config| {
+                config
+                    .try_deserialize::<Config>()
+                    .map_err(name_config_sources)
+            })
+            .map_err(|err| err.to_string());
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn config_file_sections_map_to_settings() {
+        let config = load_file(
+            "toml",
+            r##"
+[user]
+name = "octo"
+token = ["token-a", "token-b"]
+
+[filters]
+ignored_repositories = ["octo/dotfiles*"]
+ignore_affects_stars = ["topics"]
+commits_year = 2025
+
+[cache]
+seconds = 60
+
+[themes.ocean]
+background_color = "#002b36"
+"##,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(config.github_user, "octo");
+        assert_eq!(config.github_token, ["token-a", "token-b"]);
+        assert_eq!(config.ignored_repositories, ["octo/dotfiles*"]);
+        assert_eq!(config.ignore_affects_stars, [github::IgnoreRule::Topics]);
+        assert_eq!(config.commits_year, Some(2025));
+        assert_eq!(config.cache_seconds, 60);
+        assert_eq!(config.port, 8080);
+        assert_eq!(config.themes["ocean"].background_color, "#002b36");
+    }
+
+    #[test]
+    fn environment_overrides_config_file() {
+        let config = load_file(
+            "yaml",
+            "user:\n  name: octo\n  token: t\nfilters:\n  ignored_topics: [a, b]\nserver:\n  port: 9000\n",
+            &[("STATS_PORT", "9100"), ("STATS_IGNORED_TOPICS", "c")],
+        )
+        .unwrap();
+        assert_eq!(config.port, 9100);
+        assert_eq!(config.ignored_topics, ["c"]);
+        assert_eq!(config.github_user, "octo");
+    }
+
+    #[test]
+    fn config_file_errors_name_the_key() {
+        let err = load_file("toml", "[filters]\nignore_topics = []\n", &[]).unwrap_err();
+        assert!(err.contains("`filters.ignore_topics`"), "{err}");
+
+        let err = load_file(
+            "toml",
+            "[user]\nname = \"o\"\n[server]\nport = \"abc\"\n",
+            &[("STATS_GITHUB_TOKEN", "t")],
+        )
+        .unwrap_err();
+        assert!(err.contains("`server.port`"), "{err}");
+        assert!(err.contains("STATS_PORT"), "{err}");
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::rate_limit;
+
+/// Upper bounds, in seconds, of the buckets for handler latency.
+const REQUEST_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+/// Upper bounds, in seconds, of the buckets for GitHub crawls, which take far longer.
+const CRAWL_BUCKETS: &[f64] = &[1.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0];
+
+/// `HELP` text of every metric, in the order they are exposed.
+const HELP: &[(&str, &str)] = &[
+    (
+        "github_stats_http_requests_total",
+        "HTTP requests handled, by route and status.",
+    ),
+    (
+        "github_stats_http_request_duration_seconds",
+        "Time spent handling HTTP requests, by route.",
+    ),
+    (
+        "github_stats_cache_lookups_total",
+        "Stats cache lookups, by cache and result.",
+    ),
+    (
+        "github_stats_cache_age_seconds",
+        "Age of the stats cache file.",
+    ),
+    (
+        "github_stats_github_requests_total",
+        "Responses from the GitHub API by status; `error` when no response arrived.",
+    ),
+    (
+        "github_stats_github_retries_total",
+        "GitHub API requests retried, by reason.",
+    ),
+    (
+        "github_stats_github_rate_limit_sleeps_total",
+        "Times a crawl slept until a GitHub rate limit lifted.",
+    ),
+    (
+        "github_stats_github_rate_limit_sleep_seconds_total",
+        "Time spent sleeping on GitHub rate limits.",
+    ),
+    (
+        "github_stats_github_rate_limit_remaining",
+        "Requests left in the last reported rate-limit window, by credential and resource.",
+    ),
+    (
+        "github_stats_github_rate_limit_limit",
+        "Size of the last reported rate-limit window, by credential and resource.",
+    ),
+    (
+        "github_stats_github_rate_limit_reset_timestamp_seconds",
+        "Unix time at which the rate-limit window resets, by credential and resource.",
+    ),
+    (
+        "github_stats_crawl_duration_seconds",
+        "Duration of GitHub crawls, by kind and result.",
+    ),
+    (
+        "github_stats_last_successful_refresh_timestamp_seconds",
+        "Unix time of the last crawl that refreshed the stats cache.",
+    ),
+];
+
+type Labels = Vec<(&'static str, String)>;
+
+struct Histogram {
+    bounds: &'static [f64],
+    /// Observations per bucket, not cumulative; the exposition adds them up.
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Histogram {
+            bounds,
+            counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        if let Some(bucket) = self.bounds.iter().position(|&bound| value <= bound) {
+            self.counts[bucket] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    counters: BTreeMap<&'static str, BTreeMap<Labels, f64>>,
+    gauges: BTreeMap<&'static str, BTreeMap<Labels, f64>>,
+    histograms: BTreeMap<&'static str, BTreeMap<Labels, Histogram>>,
+}
+
+impl Registry {
+    fn add(&mut self, name: &'static str, labels: Labels, value: f64) {
+        *self
+            .counters
+            .entry(name)
+            .or_default()
+            .entry(labels)
+            .or_default() += value;
+    }
+
+    fn set(&mut self, name: &'static str, labels: Labels, value: f64) {
+        self.gauges.entry(name).or_default().insert(labels, value);
+    }
+
+    fn observe(&mut self, name: &'static str, bounds: &'static [f64], labels: Labels, value: f64) {
+        self.histograms
+            .entry(name)
+            .or_default()
+            .entry(labels)
+            .or_insert_with(|| Histogram::new(bounds))
+            .observe(value);
+    }
+}
+
+/// Metrics shared by all workers.
+fn registry() -> &'static Mutex<Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(Registry::default()))
+}
+
+fn labels(pairs: &[(&'static str, &str)]) -> Labels {
+    pairs
+        .iter()
+        .map(|(name, value)| (*name, value.to_string()))
+        .collect()
+}
+
+/// Records a handled request. `route` is the matched pattern, not the path, so that
+/// query strings and unknown paths cannot grow the number of series.
+pub fn http_request(route: &str, status: u16, duration: Duration) {
+    let mut registry = registry().lock().unwrap();
+    registry.add(
+        "github_stats_http_requests_total",
+        labels(&[("route", route), ("status", &status.to_string())]),
+        1.0,
+    );
+    registry.observe(
+        "github_stats_http_request_duration_seconds",
+        REQUEST_BUCKETS,
+        labels(&[("route", route)]),
+        duration.as_secs_f64(),
+    );
+}
+
+/// Records whether `cache` (`stats` or `window`) could answer without a crawl.
+pub fn cache_lookup(cache: &str, hit: bool) {
+    let result = if hit { "hit" } else { "miss" };
+    registry().lock().unwrap().add(
+        "github_stats_cache_lookups_total",
+        labels(&[("cache", cache), ("result", result)]),
+        1.0,
+    );
+}
+
+/// Records a GitHub API response, or a request that failed without one.
+pub fn github_response(status: Option<reqwest::StatusCode>) {
+    let status = status.map_or_else(|| String::from("error"), |s| s.as_u16().to_string());
+    registry().lock().unwrap().add(
+        "github_stats_github_requests_total",
+        labels(&[("status", &status)]),
+        1.0,
+    );
+}
+
+/// Records a retried GitHub API request.
+pub fn github_retry(reason: &str) {
+    registry().lock().unwrap().add(
+        "github_stats_github_retries_total",
+        labels(&[("reason", reason)]),
+        1.0,
+    );
+}
+
+/// Records a sleep waiting for a GitHub rate limit to lift.
+pub fn rate_limit_sleep(duration: Duration) {
+    let mut registry = registry().lock().unwrap();
+    registry.add(
+        "github_stats_github_rate_limit_sleeps_total",
+        Vec::new(),
+        1.0,
+    );
+    registry.add(
+        "github_stats_github_rate_limit_sleep_seconds_total",
+        Vec::new(),
+        duration.as_secs_f64(),
+    );
+}
+
+/// Records a crawl of `kind` (`stats` or `window`).
+pub fn crawl(kind: &str, duration: Duration, succeeded: bool) {
+    let result = if succeeded { "success" } else { "failure" };
+    registry().lock().unwrap().observe(
+        "github_stats_crawl_duration_seconds",
+        CRAWL_BUCKETS,
+        labels(&[("kind", kind), ("result", result)]),
+        duration.as_secs_f64(),
+    );
+}
+
+/// Marks the time of the last refresh, once a crawl's stats have been written to the cache.
+pub fn stats_refreshed() {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64());
+    registry().lock().unwrap().set(
+        "github_stats_last_successful_refresh_timestamp_seconds",
+        Vec::new(),
+        now,
+    );
+}
+
+/// Escapes a label value as the text exposition format requires.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+fn write_series(out: &mut String, name: &str, labels: &[(&str, String)], value: f64) {
+    out.push_str(name);
+    if !labels.is_empty() {
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(label, value)| format!("{label}=\"{}\"", escape(value)))
+            .collect();
+        let _ = write!(out, "{{{}}}", labels.join(","));
+    }
+    let _ = writeln!(out, " {value}");
+}
+
+/// Renders every metric in the Prometheus text format. The cache age and rate-limit
+/// budgets are read at scrape time rather than recorded.
+pub fn render(cache_age: Option<u64>) -> String {
+    let mut registry = registry().lock().unwrap();
+    if let Some(age) = cache_age {
+        registry.set("github_stats_cache_age_seconds", Vec::new(), age as f64);
+    } else {
+        registry.gauges.remove("github_stats_cache_age_seconds");
+    }
+    for name in [
+        "github_stats_github_rate_limit_remaining",
+        "github_stats_github_rate_limit_limit",
+        "github_stats_github_rate_limit_reset_timestamp_seconds",
+    ] {
+        registry.gauges.remove(name);
+    }
+    for budget in rate_limit::status() {
+        let labels = labels(&[
+            ("credential", &budget.credential),
+            ("resource", &budget.resource),
+        ]);
+        for (name, value) in [
+            ("github_stats_github_rate_limit_remaining", budget.remaining),
+            ("github_stats_github_rate_limit_limit", budget.limit),
+            (
+                "github_stats_github_rate_limit_reset_timestamp_seconds",
+                budget.reset,
+            ),
+        ] {
+            if let Some(value) = value {
+                registry.set(name, labels.clone(), value as f64);
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for (name, help) in HELP {
+        if let Some(series) = registry.counters.get(name) {
+            let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} counter");
+            for (labels, value) in series {
+                write_series(&mut out, name, labels, *value);
+            }
+        } else if let Some(series) = registry.gauges.get(name) {
+            let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} gauge");
+            for (labels, value) in series {
+                write_series(&mut out, name, labels, *value);
+            }
+        } else if let Some(series) = registry.histograms.get(name) {
+            let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} histogram");
+            for (labels, histogram) in series {
+                let bucket = format!("{name}_bucket");
+                let mut cumulative = 0;
+                let buckets = histogram
+                    .bounds
+                    .iter()
+                    .zip(&histogram.counts)
+                    .map(|(le, count)| {
+                        cumulative += count;
+                        (le.to_string(), cumulative)
+                    });
+                for (le, count) in buckets.chain([(String::from("+Inf"), histogram.count)]) {
+                    let mut labels = labels.clone();
+                    labels.push(("le", le));
+                    write_series(&mut out, &bucket, &labels, count as f64);
+                }
+                write_series(&mut out, &format!("{name}_sum"), labels, histogram.sum);
+                write_series(
+                    &mut out,
+                    &format!("{name}_count"),
+                    labels,
+                    histogram.count as f64,
+                );
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        http_request("/test/histogram", 200, Duration::from_millis(3));
+        http_request("/test/histogram", 200, Duration::from_millis(30));
+        http_request("/test/histogram", 200, Duration::from_secs(60));
+        let text = render(None);
+        let route = r#"route="/test/histogram""#;
+        for (le, count) in [("0.005", 1), ("0.05", 2), ("10", 2), ("+Inf", 3)] {
+            let line = format!(
+                "github_stats_http_request_duration_seconds_bucket{{{route},le=\"{le}\"}} {count}"
+            );
+            assert!(text.contains(&line), "missing {line}");
+        }
+        assert!(text.contains(&format!(
+            "github_stats_http_request_duration_seconds_count{{{route}}} 3"
+        )));
+        assert!(text.contains(&format!(
+            "github_stats_http_requests_total{{{route},status=\"200\"}} 3"
+        )));
+    }
+
+    #[test]
+    fn every_exposed_metric_has_help_and_type() {
+        cache_lookup("stats", true);
+        github_response(None);
+        let text = render(Some(42));
+        assert!(text.contains("github_stats_cache_age_seconds 42\n"));
+        assert!(text.contains(r#"github_stats_github_requests_total{status="error"}"#));
+        for line in text.lines().filter(|l| !l.starts_with('#')) {
+            let name = line.split(['{', ' ']).next().unwrap();
+            let family = ["_bucket", "_sum", "_count"]
+                .iter()
+                .find_map(|suffix| name.strip_suffix(suffix))
+                .filter(|family| text.contains(&format!("# TYPE {family} histogram")))
+                .unwrap_or(name);
+            assert!(text.contains(&format!("# HELP {family} ")), "{family}");
+        }
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        assert_eq!(escape("a\"b\\c\nd"), r#"a\"b\\c\nd"#);
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::dev::ServerHandle;
+use actix_web::http::StatusCode;
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+
+use crate::github::GithubApi;
+
+/// A scripted response served by [`MockGithub`].
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl MockResponse {
+    pub fn json(body: serde_json::Value) -> Self {
+        MockResponse {
+            status: 200,
+            headers: Vec::new(),
+            body: body.to_string(),
+        }
+    }
+
+    pub fn status(status: u16) -> Self {
+        MockResponse {
+            status,
+            headers: Vec::new(),
+            body: String::from(r#"{"message":"scripted error"}"#),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: impl ToString) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// A 403 with an exhausted primary rate limit that resets `reset_in` seconds from now.
+    pub fn rate_limited(reset_in: u64) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        MockResponse::status(403)
+            .header("x-ratelimit-remaining", 0)
+            .header("x-ratelimit-limit", 5000)
+            .header("x-ratelimit-reset", now + reset_in)
+    }
+}
+
+/// A request received by [`MockGithub`].
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// Path including the query string.
+    pub path: String,
+    /// Header values keyed by lowercase name.
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Default)]
+struct State {
+    routes: HashMap<String, VecDeque<MockResponse>>,
+    requests: Vec<RecordedRequest>,
+}
+
+/// In-process GitHub API stand-in listening on an ephemeral localhost port.
+///
+/// Responses are scripted per path and query; each request consumes the next response
+/// for its path, and the last one is repeated once the script runs out. Unscripted paths
+/// answer 404.
+pub struct MockGithub {
+    url: String,
+    state: Arc<Mutex<State>>,
+    handle: ServerHandle,
+}
+
+impl MockGithub {
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(State::default()));
+        let app_state = web::Data::from(state.clone());
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(app_state.clone())
+                .default_service(web::to(respond))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .expect("mock server binds to an ephemeral port");
+        let addr = server.addrs()[0];
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+        MockGithub {
+            url: format!("http://{addr}"),
+            state,
+            handle,
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn api(&self) -> GithubApi {
+        GithubApi::new(&self.url, None, None)
+    }
+
+    /// Scripts the responses for `path` (including any query string).
+    pub fn on(&self, path: &str, responses: impl IntoIterator<Item = MockResponse>) {
+        self.state
+            .lock()
+            .unwrap()
+            .routes
+            .insert(path.to_string(), responses.into_iter().collect());
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Number of requests received for `path`.
+    pub fn hits(&self, path: &str) -> usize {
+        self.requests().iter().filter(|r| r.path == path).count()
+    }
+}
+
+impl Drop for MockGithub {
+    fn drop(&mut self) {
+        // The stop command is sent immediately; waiting for shutdown is not needed.
+        drop(self.handle.stop(false));
+    }
+}
+
+async fn respond(req: HttpRequest, state: web::Data<Mutex<State>>) -> HttpResponse {
+    let path = req
+        .uri()
+        .path_and_query()
+        .map_or_else(|| req.path().to_string(), |p| p.to_string());
+    let mut state = state.lock().unwrap();
+    state.requests.push(RecordedRequest {
+        method: req.method().to_string(),
+        path: path.clone(),
+        headers: req
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect(),
+    });
+
+    let response = state.routes.get_mut(&path).and_then(|script| {
+        if script.len() > 1 {
+            script.pop_front()
+        } else {
+            script.front().cloned()
+        }
+    });
+    let Some(response) = response else {
+        return HttpResponse::NotFound().body(r#"{"message":"Not Found"}"#);
+    };
+
+    let mut builder = HttpResponse::build(StatusCode::from_u16(response.status).unwrap());
+    builder.content_type("application/json");
+    for (name, value) in &response.headers {
+        builder.insert_header((name.as_str(), value.as_str()));
+    }
+    builder.body(response.body)
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+/// Rate-limit budget last reported by GitHub for one credential and resource.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Budget {
+    pub remaining: Option<u64>,
+    pub limit: Option<u64>,
+    pub used: Option<u64>,
+    /// Unix timestamp at which the budget resets.
+    pub reset: Option<u64>,
+}
+
+impl Budget {
+    /// Remaining requests, or `None` when unknown or the window has already reset.
+    fn remaining_at(&self, now: u64) -> Option<u64> {
+        match self.reset {
+            Some(reset) if reset > now => self.remaining,
+            _ => None,
+        }
+    }
+
+    fn is_exhausted(&self, now: u64) -> bool {
+        self.remaining_at(now) == Some(0)
+    }
+}
+
+/// A recorded budget as shown by `/health`.
+#[derive(Debug, Serialize)]
+pub struct BudgetStatus {
+    pub credential: String,
+    /// `core`, `search`, `graphql`, … as reported by `x-ratelimit-resource`.
+    pub resource: String,
+    pub remaining: Option<u64>,
+    pub limit: Option<u64>,
+    pub used: Option<u64>,
+    pub reset: Option<u64>,
+    pub exhausted: bool,
+}
+
+/// A budget together with the label its credential is shown under.
+struct Entry {
+    label: String,
+    budget: Budget,
+}
+
+/// Budgets shared by all workers, keyed by credential key and resource.
+fn budgets() -> &'static Mutex<HashMap<(String, String), Entry>> {
+    static BUDGETS: OnceLock<Mutex<HashMap<(String, String), Entry>>> = OnceLock::new();
+    BUDGETS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Records the budget reported by a response made with the credential `key`, shown as
+/// `label`.
+pub fn record(key: &str, label: &str, resource: &str, budget: Budget) {
+    if budget == Budget::default() {
+        return;
+    }
+    budgets().lock().unwrap().insert(
+        (key.to_string(), resource.to_string()),
+        Entry {
+            label: label.to_string(),
+            budget,
+        },
+    );
+}
+
+pub fn is_exhausted(key: &str, resource: &str) -> bool {
+    budgets()
+        .lock()
+        .unwrap()
+        .get(&(key.to_string(), resource.to_string()))
+        .is_some_and(|e| e.budget.is_exhausted(now()))
+}
+
+/// Total remaining requests across the credential `keys`, or `None` if any of them is
+/// unknown.
+pub fn remaining(keys: &[String], resource: &str) -> Option<u64> {
+    let budgets = budgets().lock().unwrap();
+    let now = now();
+    keys.iter()
+        .map(|k| {
+            budgets
+                .get(&(k.clone(), resource.to_string()))
+                .and_then(|e| e.budget.remaining_at(now))
+        })
+        .sum()
+}
+
+/// Earliest reset among the credential `keys` for `resource`.
+pub fn next_reset(keys: &[String], resource: &str) -> Option<u64> {
+    let budgets = budgets().lock().unwrap();
+    keys.iter()
+        .filter_map(|k| budgets.get(&(k.clone(), resource.to_string())))
+        .filter_map(|e| e.budget.reset)
+        .min()
+}
+
+pub fn status() -> Vec<BudgetStatus> {
+    let budgets = budgets().lock().unwrap();
+    let now = now();
+    let mut status: Vec<BudgetStatus> = budgets
+        .iter()
+        .map(|((_, resource), Entry { label, budget })| BudgetStatus {
+            credential: label.clone(),
+            resource: resource.clone(),
+            remaining: budget.remaining,
+            limit: budget.limit,
+            used: budget.used,
+            reset: budget.reset,
+            exhausted: budget.is_exhausted(now),
+        })
+        .collect();
+    status.sort_by(|a, b| (&a.credential, &a.resource).cmp(&(&b.credential, &b.resource)));
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(remaining: u64, reset: u64) -> Budget {
+        Budget {
+            remaining: Some(remaining),
+            limit: Some(5000),
+            used: Some(5000 - remaining),
+            reset: Some(reset),
+        }
+    }
+
+    #[test]
+    fn budget_past_reset_is_unknown() {
+        assert_eq!(budget(10, 200).remaining_at(199), Some(10));
+        assert_eq!(budget(10, 200).remaining_at(200), None);
+        assert!(budget(0, 200).is_exhausted(100));
+        assert!(!budget(0, 200).is_exhausted(300));
+    }
+
+    #[test]
+    fn remaining_sums_credentials_and_requires_all_known() {
+        let far = now() + 3600;
+        record("test-a", "…a", "core", budget(10, far));
+        record("test-b", "…b", "core", budget(5, far));
+        let both = ["test-a".to_string(), "test-b".to_string()];
+        assert_eq!(remaining(&both, "core"), Some(15));
+        assert_eq!(remaining(&both, "search"), None);
+        assert_eq!(next_reset(&both, "core"), Some(far));
+
+        let with_unknown = ["test-a".to_string(), "test-unknown".to_string()];
+        assert_eq!(remaining(&with_unknown, "core"), None);
+    }
+
+    #[test]
+    fn record_ignores_responses_without_headers() {
+        record("test-empty", "…empty", "core", Budget::default());
+        assert!(!status().iter().any(|s| s.credential == "…empty"));
+    }
+}
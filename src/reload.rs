@@ -0,0 +1,259 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_web::web::Data;
+use log::{debug, error, info, warn};
+
+use crate::{invalidate_cached_stats, remove_window_caches, stats_cache_path, themes, Config};
+
+/// How often the config file's modification time is checked.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Bumped whenever a reload invalidates cached stats.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Unix time at which the running configuration was loaded.
+static LOADED_AT: AtomicU64 = AtomicU64::new(0);
+
+/// When the running configuration was loaded. Cards rendered since may differ from
+/// earlier ones, e.g. in a custom theme, even though the cached stats did not change.
+pub fn loaded_at() -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(LOADED_AT.load(Ordering::Acquire))
+}
+
+fn mark_loaded() {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    LOADED_AT.store(now, Ordering::Release);
+}
+
+/// The current config generation. A crawl that sees it change while running was made
+/// under settings that no longer apply, so its result must not be cached.
+pub fn generation() -> u64 {
+    GENERATION.load(Ordering::Acquire)
+}
+
+/// The configuration handlers read at the start of each request. Requests already in
+/// flight, including crawls, keep the snapshot they started with.
+pub struct SharedConfig {
+    current: RwLock<Arc<Config>>,
+}
+
+impl SharedConfig {
+    pub fn new(config: Config) -> Self {
+        mark_loaded();
+        SharedConfig {
+            current: RwLock::new(Arc::new(config)),
+        }
+    }
+
+    pub fn current(&self) -> Arc<Config> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Swaps in `config` and returns the one it replaced.
+    fn replace(&self, config: Config) -> Arc<Config> {
+        std::mem::replace(&mut *self.current.write().unwrap(), Arc::new(config))
+    }
+}
+
+/// Settings that change what a crawl returns, so stats cached under the old values are stale.
+fn crawl_settings_changed(old: &Config, new: &Config) -> bool {
+    old.github_user != new.github_user
+        || old.github_token != new.github_token
+        || old.github_app_id != new.github_app_id
+        || old.github_app_private_key != new.github_app_private_key
+        || old.github_app_installation_id != new.github_app_installation_id
+        || old.github_api_url != new.github_api_url
+        || old.github_graphql_url != new.github_graphql_url
+        || old.ignored_repositories != new.ignored_repositories
+        || old.ignored_topics != new.ignored_topics
+        || old.ignored_languages != new.ignored_languages
+        || old.ignore_archived != new.ignore_archived
+        || old.ignore_templates != new.ignore_templates
+        || old.ignore_affects_stars != new.ignore_affects_stars
+        || old.exclude_forks != new.exclude_forks
+        || old.include_private != new.include_private
+        || old.include_archived != new.include_archived
+        || old.include_org_repos != new.include_org_repos
+        || old.commit_count != new.commit_count
+        || old.commits_year != new.commits_year
+}
+
+/// Settings only read at startup.
+fn restart_settings_changed(old: &Config, new: &Config) -> Vec<&'static str> {
+    [
+        ("ipv4_address", old.ipv4_address != new.ipv4_address),
+        ("ipv6_address", old.ipv6_address != new.ipv6_address),
+        ("port", old.port != new.port),
+        (
+            "rate limits",
+            (
+                old.rate_limit_per_minute,
+                old.rate_limit_burst,
+                old.rate_limit_refresh_per_minute,
+                old.rate_limit_refresh_burst,
+                old.rate_limit_metrics_per_minute,
+                old.rate_limit_metrics_burst,
+                old.rate_limit_webhook_per_minute,
+                old.rate_limit_webhook_burst,
+            ) != (
+                new.rate_limit_per_minute,
+                new.rate_limit_burst,
+                new.rate_limit_refresh_per_minute,
+                new.rate_limit_refresh_burst,
+                new.rate_limit_metrics_per_minute,
+                new.rate_limit_metrics_burst,
+                new.rate_limit_webhook_per_minute,
+                new.rate_limit_webhook_burst,
+            ),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(name, changed)| changed.then_some(name))
+    .collect()
+}
+
+/// Re-reads the configuration and swaps it in, keeping the old one if it is invalid.
+pub fn reload(shared: &SharedConfig, file: Option<&Path>) {
+    let config = match Config::load(file) {
+        Ok(config) => config,
+        Err(err) => {
+            error!("Keeping the current configuration, reload failed: {err}");
+            return;
+        }
+    };
+    themes::register(&config.themes);
+    let new_cache_path = config.cache_path.clone();
+    let stale = {
+        let old = shared.current();
+        for name in restart_settings_changed(&old, &config) {
+            warn!("Changing {name} takes effect after a restart");
+        }
+        crawl_settings_changed(&old, &config)
+    };
+    shared.replace(config);
+    mark_loaded();
+    info!("Configuration reloaded");
+
+    if stale {
+        // After the swap, so a crawl that read the old config sees a newer generation.
+        GENERATION.fetch_add(1, Ordering::AcqRel);
+        remove_window_caches(&new_cache_path);
+        let path = stats_cache_path(&new_cache_path);
+        match invalidate_cached_stats(&path) {
+            Ok(()) => info!("Cached stats invalidated by the new configuration"),
+            Err(err) => debug!("No cached stats to invalidate: {err}"),
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Reloads the configuration on SIGHUP and whenever `file` changes on disk.
+pub async fn watch(shared: Data<SharedConfig>, file: Option<PathBuf>) {
+    #[cfg(unix)]
+    let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+        Ok(signal) => Some(signal),
+        Err(err) => {
+            warn!("SIGHUP reload unavailable: {err}");
+            None
+        }
+    };
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    let mut last_modified = file.as_deref().and_then(modified);
+
+    loop {
+        #[cfg(unix)]
+        let hangup_received = async {
+            match hangup.as_mut() {
+                Some(signal) => signal.recv().await,
+                None => std::future::pending().await,
+            }
+        };
+        #[cfg(not(unix))]
+        let hangup_received = std::future::pending::<Option<()>>();
+
+        tokio::select! {
+            _ = hangup_received => {
+                info!("Received SIGHUP, reloading configuration");
+                reload(&shared, file.as_deref());
+            }
+            _ = interval.tick() => {
+                let Some(file) = file.as_deref() else { continue };
+                let current = modified(file);
+                if current != last_modified {
+                    last_modified = current;
+                    info!("{} changed, reloading configuration", file.display());
+                    reload(&shared, Some(file));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_changes_invalidate_cached_stats() {
+        let old = Config::for_tests(&[]);
+        assert!(!crawl_settings_changed(
+            &old,
+            &Config::for_tests(&[("cache_seconds", "60")])
+        ));
+        assert!(crawl_settings_changed(
+            &old,
+            &Config::for_tests(&[("ignored_topics", "dotfiles")])
+        ));
+        assert!(crawl_settings_changed(
+            &old,
+            &Config::for_tests(&[("commit_count", "graphql")])
+        ));
+    }
+
+    #[test]
+    fn credential_changes_invalidate_cached_stats() {
+        let old = Config::for_tests(&[]);
+        assert!(crawl_settings_changed(
+            &old,
+            &Config::for_tests(&[("github_token", "other")])
+        ));
+        assert!(crawl_settings_changed(
+            &old,
+            &Config::for_tests(&[("github_app_id", "7")])
+        ));
+        assert!(crawl_settings_changed(
+            &old,
+            &Config::for_tests(&[("github_app_installation_id", "42")])
+        ));
+    }
+
+    #[test]
+    fn listener_changes_need_a_restart() {
+        let old = Config::for_tests(&[]);
+        assert!(
+            restart_settings_changed(&old, &Config::for_tests(&[("cache_seconds", "60")]))
+                .is_empty()
+        );
+        assert_eq!(
+            restart_settings_changed(&old, &Config::for_tests(&[("port", "9000")])),
+            ["port"]
+        );
+    }
+
+    #[test]
+    fn replace_keeps_earlier_snapshots_intact() {
+        let shared = SharedConfig::new(Config::for_tests(&[]));
+        let before = shared.current();
+        shared.replace(Config::for_tests(&[("cache_seconds", "60")]));
+        assert_eq!(before.cache_seconds, 86400);
+        assert_eq!(shared.current().cache_seconds, 60);
+    }
+}
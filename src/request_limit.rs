@@ -0,0 +1,199 @@
+use std::net::IpAddr;
+use std::time::Duration;
+
+use actix_governor::governor::clock::QuantaInstant;
+use actix_governor::governor::middleware::NoOpMiddleware;
+use actix_governor::{
+    Governor, GovernorConfig, GovernorConfigBuilder, KeyExtractor, SimpleKeyExtractionError,
+};
+use actix_web::dev::ServiceRequest;
+use actix_web::middleware::Condition;
+use actix_web::web::Data;
+
+use crate::client_ip::client_ip;
+use crate::reload::SharedConfig;
+
+/// Which limit a request counts against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bucket {
+    Cards,
+    Refresh,
+    Metrics,
+    /// Webhook deliveries: limited before their signature is checked, but more generously
+    /// than `/refresh`, since the refreshes they trigger are debounced.
+    Webhook,
+}
+
+impl Bucket {
+    fn of(path: &str) -> Self {
+        match path {
+            "/refresh" => Bucket::Refresh,
+            "/metrics" => Bucket::Metrics,
+            "/webhook/github" => Bucket::Webhook,
+            _ => Bucket::Cards,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClientKey {
+    Client(IpAddr),
+    /// Not limited: an exempt client, or a request in another bucket.
+    Exempt,
+}
+
+/// Keys requests by client address, resolved through the trusted proxies.
+///
+/// Each limiter sees every request, so requests outside its bucket are keyed as exempt.
+/// The proxies and exemptions are read from the current configuration, so they follow
+/// reloads.
+#[derive(Debug, Clone)]
+pub struct ClientKeyExtractor {
+    bucket: Bucket,
+}
+
+impl KeyExtractor for ClientKeyExtractor {
+    type Key = ClientKey;
+    type KeyExtractionError = SimpleKeyExtractionError<&'static str>;
+
+    fn extract(&self, req: &ServiceRequest) -> Result<Self::Key, Self::KeyExtractionError> {
+        if Bucket::of(req.path()) != self.bucket {
+            return Ok(ClientKey::Exempt);
+        }
+        let peer = req.peer_addr().map(|addr| addr.ip()).ok_or_else(|| {
+            SimpleKeyExtractionError::new("Could not extract peer IP address from request")
+        })?;
+        let Some(config) = req.app_data::<Data<SharedConfig>>().map(|c| c.current()) else {
+            return Ok(ClientKey::Client(peer));
+        };
+        let ip = client_ip(peer, req.headers(), &config.trusted_proxies);
+        if config.rate_limit_exempt.iter().any(|net| net.contains(ip)) {
+            return Ok(ClientKey::Exempt);
+        }
+        Ok(ClientKey::Client(limit_prefix(ip)))
+    }
+
+    fn whitelisted_keys(&self) -> Vec<Self::Key> {
+        vec![ClientKey::Exempt]
+    }
+}
+
+/// IPv6 clients usually get a whole /56, so they are limited per prefix.
+fn limit_prefix(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(ipv6) => {
+            let mut octets = ipv6.octets();
+            octets[7..].fill(0);
+            IpAddr::V6(octets.into())
+        }
+        ip => ip,
+    }
+}
+
+/// A per-client rate limit for one bucket, shared by all workers.
+#[derive(Clone)]
+pub struct Limiter {
+    config: GovernorConfig<ClientKeyExtractor, NoOpMiddleware<QuantaInstant>>,
+    enabled: bool,
+}
+
+impl Limiter {
+    /// Allows `per_minute` requests per client, up to `burst` at once; 0 disables the limit.
+    pub fn new(bucket: Bucket, per_minute: u32, burst: u32) -> Self {
+        let config = GovernorConfigBuilder::default()
+            .period(Duration::from_secs(60) / per_minute.max(1))
+            .burst_size(burst.max(1))
+            .key_extractor(ClientKeyExtractor { bucket })
+            .finish()
+            .expect("period and burst size are never zero");
+        Limiter {
+            config,
+            enabled: per_minute > 0,
+        }
+    }
+
+    pub fn middleware(
+        &self,
+    ) -> Condition<Governor<ClientKeyExtractor, NoOpMiddleware<QuantaInstant>>> {
+        Condition::new(self.enabled, Governor::new(&self.config))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{http::StatusCode, web, App, HttpResponse};
+
+    use super::*;
+
+    #[test]
+    fn ipv6_clients_share_their_prefix() {
+        let ip: IpAddr = "2001:db8:1:2:3:4:5:6".parse().unwrap();
+        assert_eq!(limit_prefix(ip), "2001:db8:1::".parse::<IpAddr>().unwrap());
+        let ip: IpAddr = "192.0.2.7".parse().unwrap();
+        assert_eq!(limit_prefix(ip), ip);
+    }
+
+    #[test]
+    fn webhook_deliveries_have_their_own_bucket() {
+        assert_eq!(Bucket::of("/webhook/github"), Bucket::Webhook);
+        assert_eq!(Bucket::of("/refresh"), Bucket::Refresh);
+        assert_eq!(Bucket::of("/stats"), Bucket::Cards);
+    }
+
+    #[actix_web::test]
+    async fn buckets_are_limited_separately() {
+        let cards = Limiter::new(Bucket::Cards, 60, 1);
+        let refresh = Limiter::new(Bucket::Refresh, 60, 1);
+        let app = init_service(
+            App::new()
+                .wrap(cards.middleware())
+                .wrap(refresh.middleware())
+                .route("/stats", web::get().to(HttpResponse::Ok))
+                .route("/refresh", web::post().to(HttpResponse::Ok)),
+        )
+        .await;
+        let peer = "192.0.2.7:1234".parse().unwrap();
+        let stats = || {
+            TestRequest::get()
+                .uri("/stats")
+                .peer_addr(peer)
+                .to_request()
+        };
+        let refresh = || {
+            TestRequest::post()
+                .uri("/refresh")
+                .peer_addr(peer)
+                .to_request()
+        };
+
+        assert_eq!(call_service(&app, stats()).await.status(), StatusCode::OK);
+        assert_eq!(
+            call_service(&app, stats()).await.status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(call_service(&app, refresh()).await.status(), StatusCode::OK);
+        assert_eq!(
+            call_service(&app, refresh()).await.status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+
+    #[actix_web::test]
+    async fn disabled_limiter_lets_everything_through() {
+        let app = init_service(
+            App::new()
+                .wrap(Limiter::new(Bucket::Cards, 0, 0).middleware())
+                .route("/stats", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let peer = "192.0.2.8:1234".parse().unwrap();
+        for _ in 0..5 {
+            let req = TestRequest::get()
+                .uri("/stats")
+                .peer_addr(peer)
+                .to_request();
+            assert_eq!(call_service(&app, req).await.status(), StatusCode::OK);
+        }
+    }
+}
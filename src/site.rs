@@ -0,0 +1,248 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::card::xml_escape;
+use crate::{
+    badge, build_combined_svg, build_languages_svg, build_stats_svg, github, sorted_repositories,
+    themes, CardQuery,
+};
+
+/// Card variants written by [`write_site`].
+pub struct SiteOptions<'a> {
+    pub themes: &'a [String],
+    pub widths: &'a [f64],
+    pub lang_count: Option<usize>,
+    pub private_marker: bool,
+    /// `cacheSeconds` advertised to shields.io by the badge JSON files.
+    pub cache_seconds: u32,
+}
+
+type CardBuilder = fn(&github::Stats, &CardQuery) -> String;
+
+const CARDS: [(&str, &str, CardBuilder); 3] = [
+    ("stats", "Stats", build_stats_svg),
+    ("languages", "Top languages", build_languages_svg),
+    ("combined", "Combined", build_lifetime_combined_svg),
+];
+
+fn build_lifetime_combined_svg(stats: &github::Stats, query: &CardQuery) -> String {
+    build_combined_svg(stats, None, query)
+}
+
+/// A file written to the site, relative to its root.
+struct Page {
+    path: String,
+    contents: String,
+}
+
+/// Renders every card for each theme and width, the badges, and the JSON files the server
+/// would answer with, plus an `index.html` linking them all.
+fn pages(stats: &github::Stats, options: &SiteOptions) -> Result<Vec<Page>, serde_json::Error> {
+    let mut pages = Vec::new();
+    let mut gallery = String::new();
+
+    for (card, title, build) in CARDS {
+        let _ = write!(gallery, "<h2>{title}</h2>\n<div class=\"cards\">\n");
+        for theme in options.themes {
+            for &width in options.widths {
+                let query = CardQuery {
+                    theme: Some(theme.clone()),
+                    width: Some(width),
+                    lang_count: options.lang_count,
+                    private_marker: Some(options.private_marker),
+                    ..CardQuery::default()
+                };
+                let path = format!("{card}-{theme}-{}.svg", query.width());
+                gallery_item(
+                    &mut gallery,
+                    &path,
+                    &format!("{theme}, {}px", query.width()),
+                );
+                pages.push(Page {
+                    path,
+                    contents: build(stats, &query),
+                });
+            }
+        }
+        gallery.push_str("</div>\n");
+    }
+
+    gallery.push_str("<h2>Badges</h2>\n<div class=\"cards\">\n");
+    for theme_name in options.themes {
+        let theme = themes::from_name(theme_name);
+        for metric in badge::Metric::ALL {
+            let path = format!("badge-{}-{theme_name}.svg", metric.name());
+            gallery_item(
+                &mut gallery,
+                &path,
+                &format!("{}, {theme_name}", metric.label()),
+            );
+            let badge = badge::Badge::for_metric(metric, stats, &theme);
+            pages.push(Page {
+                path,
+                contents: badge::render_badge(&badge, &theme),
+            });
+        }
+    }
+    gallery.push_str("</div>\n");
+
+    // Same paths as the server's JSON endpoints, so shields.io can use the published files.
+    let default_theme = themes::dark();
+    for metric in badge::Metric::ALL {
+        let badge = badge::Badge::for_metric(metric, stats, &default_theme);
+        let endpoint = badge::ShieldsEndpoint::new(badge, false, options.cache_seconds);
+        pages.push(Page {
+            path: format!("badge/{}.json", metric.name()),
+            contents: serde_json::to_string(&endpoint)?,
+        });
+    }
+    // The site is public, so private repositories are left out of the breakdowns; the
+    // totals on the cards still count them.
+    pages.push(Page {
+        path: String::from("repositories.json"),
+        contents: serde_json::to_string_pretty(&sorted_repositories(stats, false))?,
+    });
+    let mut public = stats.clone();
+    public.repositories.retain(|_, repo| !repo.private);
+    pages.push(Page {
+        path: String::from("stats.json"),
+        contents: serde_json::to_string_pretty(&public)?,
+    });
+
+    pages.push(Page {
+        path: String::from("index.html"),
+        contents: format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>GitHub stats</title>
+<style>
+body {{ font-family: 'Segoe UI', Ubuntu, 'Helvetica Neue', Arial, sans-serif; margin: 2rem; background: #888; }}
+.cards {{ display: flex; flex-wrap: wrap; gap: 1rem; align-items: flex-start; }}
+figure {{ margin: 0; }}
+figcaption {{ font-size: 0.8rem; }}
+</style>
+</head>
+<body>
+<h1>GitHub stats</h1>
+<p>JSON: <a href="stats.json">stats.json</a>, <a href="repositories.json">repositories.json</a>, <a href="badge/stars.json">badge/stars.json</a>, <a href="badge/commits.json">badge/commits.json</a>, <a href="badge/top-language.json">badge/top-language.json</a></p>
+{gallery}</body>
+</html>
+"#
+        ),
+    });
+    Ok(pages)
+}
+
+fn gallery_item(gallery: &mut String, path: &str, caption: &str) {
+    let path = xml_escape(path);
+    let caption = xml_escape(caption);
+    let _ = writeln!(
+        gallery,
+        r#"<figure><a href="{path}"><img src="{path}" alt="{caption}"></a><figcaption>{caption}</figcaption></figure>"#
+    );
+}
+
+/// Writes the site to `dir`, creating it if needed, and returns the number of files written.
+pub fn write_site(dir: &Path, stats: &github::Stats, options: &SiteOptions) -> io::Result<usize> {
+    let pages = pages(stats, options)?;
+    for page in &pages {
+        let path = dir.join(&page.path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, &page.contents)?;
+    }
+    Ok(pages.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    fn make_stats() -> github::Stats {
+        github::Stats {
+            total_stars: 12,
+            total_commits: 34,
+            languages: HashMap::new(),
+            commits_year: None,
+            includes_private: false,
+            repositories: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn pages_cover_every_variant() {
+        let themes = [String::from("dark"), String::from("light")];
+        let options = SiteOptions {
+            themes: &themes,
+            widths: &[300.0, 500.0],
+            lang_count: None,
+            private_marker: false,
+            cache_seconds: 3600,
+        };
+        let pages = pages(&make_stats(), &options).unwrap();
+        let paths: Vec<&str> = pages.iter().map(|p| p.path.as_str()).collect();
+
+        // 3 cards × 2 themes × 2 widths, 3 badges × 2 themes, 3 badge JSON, 2 JSON, index.
+        assert_eq!(pages.len(), 12 + 6 + 3 + 2 + 1);
+        assert!(paths.contains(&"combined-light-500.svg"));
+        assert!(paths.contains(&"badge-top-language-dark.svg"));
+        assert!(paths.contains(&"badge/stars.json"));
+
+        let index = &pages.last().unwrap().contents;
+        for path in paths.iter().filter(|p| p.ends_with(".svg")) {
+            assert!(index.contains(&format!(r#"src="{path}""#)), "{path}");
+        }
+    }
+
+    #[test]
+    fn json_pages_leave_out_private_repositories() {
+        let mut stats = make_stats();
+        stats.includes_private = true;
+        for (name, private) in [("octo/public", false), ("octo/secret", true)] {
+            let repo = github::RepositoryStats {
+                private,
+                ..Default::default()
+            };
+            stats.repositories.insert(name.to_string(), repo);
+        }
+        let options = SiteOptions {
+            themes: &[],
+            widths: &[],
+            lang_count: None,
+            private_marker: false,
+            cache_seconds: 3600,
+        };
+        let pages = pages(&stats, &options).unwrap();
+        for path in ["repositories.json", "stats.json"] {
+            let page = pages.iter().find(|p| p.path == path).unwrap();
+            assert!(page.contents.contains("octo/public"), "{path}");
+            assert!(!page.contents.contains("octo/secret"), "{path}");
+        }
+    }
+
+    #[test]
+    fn write_site_creates_nested_directories() {
+        let dir = TestDir::new("site-nested-dirs");
+        let dir = dir.path();
+        let themes = [String::from("dark")];
+        let options = SiteOptions {
+            themes: &themes,
+            widths: &[300.0],
+            lang_count: Some(4),
+            private_marker: false,
+            cache_seconds: 3600,
+        };
+        let written = write_site(dir, &make_stats(), &options).unwrap();
+        assert_eq!(written, 3 + 3 + 3 + 2 + 1);
+        let badge = fs::read_to_string(dir.join("badge/commits.json")).unwrap();
+        assert!(badge.contains(r#""message":"34""#));
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use actix_web::web;
+use resvg::{tiny_skia, usvg};
+
+use crate::{
+    badge, build_combined_svg, build_languages_svg, build_stats_svg, build_window_stats_svg, card,
+    date, github, CardQuery,
+};
+
+/// Golden files live next to the sources so changes to them show up in review.
+fn snapshot_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots")
+}
+
+fn query(query: &str) -> CardQuery {
+    web::Query::<CardQuery>::from_query(query)
+        .unwrap()
+        .into_inner()
+}
+
+fn fixture_stats() -> github::Stats {
+    let languages = [
+        ("Rust", "#dea584", 52_000.0),
+        ("TypeScript", "#3178c6", 31_000.0),
+        ("Python", "#3572A5", 12_500.0),
+        ("Shell", "#89e051", 4_200.0),
+        ("HTML", "#e34c26", 2_100.0),
+        ("Go", "#00ADD8", 1_300.0),
+        ("C", "#555555", 800.0),
+        ("Dockerfile", "#384d54", 150.0),
+    ]
+    .into_iter()
+    .map(|(name, color, size)| {
+        (
+            name.to_string(),
+            github::Language {
+                name: name.to_string(),
+                color: color.to_string(),
+                size,
+            },
+        )
+    })
+    .collect();
+    github::Stats {
+        total_stars: 1234,
+        total_commits: 5678,
+        languages,
+        commits_year: None,
+        includes_private: true,
+        repositories: HashMap::new(),
+    }
+}
+
+fn fixture_window() -> (github::WindowStats, github::Window) {
+    let window_stats = github::WindowStats {
+        since: "2025-01-01".to_string(),
+        until: "2025-12-31".to_string(),
+        commits: 812,
+        pull_requests: 47,
+        contributions: 1093,
+    };
+    let window = github::Window {
+        since: date::Date::first_of_year(2025),
+        until: date::Date::last_of_year(2025),
+    };
+    (window_stats, window)
+}
+
+/// Fails unless `svg` parses as XML with an `<svg>` root element.
+fn check_well_formed(svg: &str) -> Result<(), String> {
+    let document =
+        roxmltree::Document::parse(svg).map_err(|err| format!("not well-formed: {err}"))?;
+    match document.root_element().tag_name().name() {
+        "svg" => Ok(()),
+        other => Err(format!("root element is <{other}>, expected <svg>")),
+    }
+}
+
+/// Renders `svg` the way a browser would show the card once its fade-in animations finish.
+fn rasterize(svg: &str) -> tiny_skia::Pixmap {
+    let mut options = usvg::Options::default();
+    let fontdb = options.fontdb_mut();
+    fontdb.load_system_fonts();
+    // usvg resolves `sans-serif` to Arial, which is often not installed; any sans face will do.
+    let sans = fontdb
+        .faces()
+        .flat_map(|face| &face.families)
+        .map(|(family, _)| family.clone())
+        .find(|family| family.contains("Sans") && !family.contains("Mono"));
+    if let Some(sans) = sans {
+        fontdb.set_sans_serif_family(sans);
+    }
+    // Animations are not rendered, so elements that fade in would stay invisible.
+    options.style_sheet = Some(String::from(".stagger { opacity: 1 !important; }"));
+    let tree = usvg::Tree::from_str(svg, &options).unwrap();
+    let size = tree.size().to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).unwrap();
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    pixmap
+}
+
+/// Rasterizes both SVGs and writes `{name}.diff.png`, with differing pixels in red over a
+/// faded copy of the golden image. Returns the number of differing pixels.
+fn raster_diff(name: &str, golden: &str, actual: &str) -> usize {
+    let golden = rasterize(golden);
+    let actual = rasterize(actual);
+    let width = golden.width().max(actual.width());
+    let height = golden.height().max(actual.height());
+    let mut diff = tiny_skia::Pixmap::new(width, height).unwrap();
+    let mut differing = 0;
+    for y in 0..height {
+        for x in 0..width {
+            let expected = golden.pixel(x, y);
+            let pixel = if expected == actual.pixel(x, y) {
+                expected.map_or(tiny_skia::PremultipliedColorU8::TRANSPARENT, |c| {
+                    tiny_skia::PremultipliedColorU8::from_rgba(
+                        c.red() / 4,
+                        c.green() / 4,
+                        c.blue() / 4,
+                        c.alpha() / 4,
+                    )
+                    .unwrap()
+                })
+            } else {
+                differing += 1;
+                tiny_skia::PremultipliedColorU8::from_rgba(255, 0, 0, 255).unwrap()
+            };
+            diff.pixels_mut()[(y * width + x) as usize] = pixel;
+        }
+    }
+    diff.save_png(snapshot_dir().join(format!("{name}.diff.png")))
+        .unwrap();
+    differing
+}
+
+/// Compares each rendered SVG with its golden file `tests/snapshots/{name}.svg`.
+///
+/// A mismatching render is written to `{name}.actual.svg`. With `UPDATE_SNAPSHOTS=1` the
+/// golden files are rewritten instead; with `SNAPSHOT_RASTER=1` a markup change only fails
+/// if it renders differently, and a `{name}.diff.png` shows where.
+fn assert_snapshots(cases: Vec<(String, String)>) {
+    let dir = snapshot_dir();
+    let update = env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let raster = env::var_os("SNAPSHOT_RASTER").is_some();
+    let mut failures = Vec::new();
+
+    for (name, svg) in cases {
+        if let Err(err) = check_well_formed(&svg) {
+            failures.push(format!("{name}: {err}"));
+            continue;
+        }
+        let golden_path = dir.join(format!("{name}.svg"));
+        let actual_path = dir.join(format!("{name}.actual.svg"));
+        if update {
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(&golden_path, &svg).unwrap();
+            let _ = fs::remove_file(&actual_path);
+            continue;
+        }
+        let Ok(golden) = fs::read_to_string(&golden_path) else {
+            failures.push(format!(
+                "{name}: no golden file, run with UPDATE_SNAPSHOTS=1 to create it"
+            ));
+            continue;
+        };
+        if golden == svg {
+            let _ = fs::remove_file(&actual_path);
+            continue;
+        }
+        fs::write(&actual_path, &svg).unwrap();
+        if raster {
+            match raster_diff(&name, &golden, &svg) {
+                0 => {}
+                pixels => failures.push(format!(
+                    "{name}: {pixels} pixels differ, see {name}.diff.png"
+                )),
+            }
+        } else {
+            failures.push(format!(
+                "{name}: differs from the golden file, see {}",
+                actual_path.display()
+            ));
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+const THEMES: [&str; 2] = ["dark", "light"];
+
+#[test]
+fn stats_card_snapshots() {
+    let stats = fixture_stats();
+    let mut year_stats = fixture_stats();
+    year_stats.commits_year = Some(2025);
+    let mut cases = Vec::new();
+    for theme in THEMES {
+        for width in [220, 300, 500] {
+            cases.push((
+                format!("stats_{theme}_w{width}"),
+                build_stats_svg(&stats, &query(&format!("theme={theme}&width={width}"))),
+            ));
+        }
+    }
+    cases.push((
+        String::from("stats_commits_year"),
+        build_stats_svg(&year_stats, &query("")),
+    ));
+    cases.push((
+        String::from("stats_private_marker"),
+        build_stats_svg(&stats, &query("private_marker=true")),
+    ));
+    assert_snapshots(cases);
+}
+
+#[test]
+fn window_stats_card_snapshots() {
+    let (window_stats, window) = fixture_window();
+    let cases = THEMES
+        .into_iter()
+        .map(|theme| {
+            (
+                format!("window_stats_{theme}"),
+                build_window_stats_svg(&window_stats, &window, &query(&format!("theme={theme}"))),
+            )
+        })
+        .collect();
+    assert_snapshots(cases);
+}
+
+#[test]
+fn languages_card_snapshots() {
+    let stats = fixture_stats();
+    let mut cases = Vec::new();
+    for theme in THEMES {
+        // One column up to four languages, two columns above.
+        for lang_count in [1, 4, 5, 8] {
+            cases.push((
+                format!("languages_{theme}_n{lang_count}"),
+                build_languages_svg(
+                    &stats,
+                    &query(&format!("theme={theme}&lang_count={lang_count}")),
+                ),
+            ));
+        }
+    }
+    cases.push((
+        String::from("languages_w500"),
+        build_languages_svg(&stats, &query("width=500")),
+    ));
+    let empty = github::Stats {
+        languages: HashMap::new(),
+        ..fixture_stats()
+    };
+    cases.push((
+        String::from("languages_empty"),
+        build_languages_svg(&empty, &query("")),
+    ));
+    assert_snapshots(cases);
+}
+
+#[test]
+fn combined_card_snapshots() {
+    let stats = fixture_stats();
+    let (window_stats, window) = fixture_window();
+    let mut cases = Vec::new();
+    for theme in THEMES {
+        cases.push((
+            format!("combined_{theme}"),
+            build_combined_svg(&stats, None, &query(&format!("theme={theme}"))),
+        ));
+    }
+    cases.push((
+        String::from("combined_window_n4"),
+        build_combined_svg(
+            &stats,
+            Some((&window_stats, &window)),
+            &query("lang_count=4"),
+        ),
+    ));
+    cases.push((
+        String::from("combined_w500"),
+        build_combined_svg(&stats, None, &query("width=500")),
+    ));
+    assert_snapshots(cases);
+}
+
+#[test]
+fn badge_and_error_snapshots() {
+    let stats = fixture_stats();
+    let mut cases = Vec::new();
+    for theme_name in THEMES {
+        let theme = crate::themes::from_name(theme_name);
+        for metric in ["stars", "commits", "top-language"] {
+            let badge =
+                badge::Badge::for_metric(badge::Metric::from_name(metric).unwrap(), &stats, &theme);
+            cases.push((
+                format!("badge_{metric}_{theme_name}"),
+                badge::render_badge(&badge, &theme),
+            ));
+        }
+        cases.push((
+            format!("error_{theme_name}"),
+            card::render_error_card(
+                "GitHub rate limit reached, retry after 12:34 UTC",
+                300.0,
+                &theme,
+            ),
+        ));
+    }
+    let badge = badge::Badge::error(badge::Metric::Stars, "rate limited", &crate::themes::dark());
+    cases.push((
+        String::from("badge_error"),
+        badge::render_badge(&badge, &crate::themes::dark()),
+    ));
+    assert_snapshots(cases);
+}
+
+#[test]
+fn well_formed_check_rejects_broken_markup() {
+    assert!(check_well_formed("<svg xmlns='http://www.w3.org/2000/svg'><g></svg>").is_err());
+    assert!(check_well_formed("<g/>").is_err());
+    assert!(check_well_formed("<svg xmlns='http://www.w3.org/2000/svg'/>").is_ok());
+}
@@ -1,13 +1,14 @@
 use crate::{
     card::Part,
-    icons::{icon_commits, icon_star},
+    github::WindowStats,
+    icons::{icon_commits, icon_contributions, icon_pull_requests, icon_star},
     themes::Theme,
 };
 
 const ROW_HEIGHT: f64 = 20.0;
 const ROW_GAP: f64 = 5.0;
 
-fn format_number(num: u32) -> String {
+pub(crate) fn format_number(num: u32) -> String {
     if num < 1000 {
         num.to_string()
     } else if num < 1_000_000 {
@@ -21,23 +22,64 @@ pub fn render_stats(
     theme: &Theme,
     total_stars: u32,
     total_commits: u32,
+    commits_year: Option<u16>,
     content_width: f64,
 ) -> Part {
-    let nodes: f64 = 2.0;
-    let height: f64 = nodes * ROW_HEIGHT + (nodes - 1.0) * ROW_GAP;
+    let commits_label = match commits_year {
+        Some(year) => format!("Commits in {year}"),
+        None => String::from("Total Commits"),
+    };
+    render_rows(
+        theme,
+        &[
+            (icon_star(), String::from("Total Stars"), total_stars),
+            (icon_commits(), commits_label, total_commits),
+        ],
+        content_width,
+    )
+}
+
+/// Renders the contribution counts of a time window (commits, pull requests, contributions).
+pub fn render_window_stats(theme: &Theme, window_stats: &WindowStats, content_width: f64) -> Part {
+    render_rows(
+        theme,
+        &[
+            (
+                icon_commits(),
+                String::from("Commits"),
+                window_stats.commits,
+            ),
+            (
+                icon_pull_requests(),
+                String::from("Pull Requests"),
+                window_stats.pull_requests,
+            ),
+            (
+                icon_contributions(),
+                String::from("Contributions"),
+                window_stats.contributions,
+            ),
+        ],
+        content_width,
+    )
+}
+
+/// Renders one `(icon, label, value)` row per entry, stacked vertically.
+fn render_rows(theme: &Theme, rows: &[(String, String, u32)], content_width: f64) -> Part {
+    let nodes = rows.len() as f64;
+    let height: f64 = nodes * ROW_HEIGHT + (nodes - 1.0).max(0.0) * ROW_GAP;
     let text_color = &theme.text_color;
     let title_color = &theme.title_color;
     let icon_color = &theme.icon_color;
 
-    let stars_text_node =
-        create_text_node(&icon_star(), "Total Stars", total_stars, 0, content_width);
-    let commits_text_node = create_text_node(
-        &icon_commits(),
-        "Total Commits",
-        total_commits,
-        1,
-        content_width,
-    );
+    let text_nodes: String = rows
+        .iter()
+        .enumerate()
+        .map(|(index, (icon, label, value))| {
+            create_text_node(icon, label, *value, index as u64, content_width)
+        })
+        .collect::<Vec<_>>()
+        .join("\n    ");
 
     let css_styles = format!(
         r#"
@@ -74,8 +116,7 @@ pub fn render_stats(
     <style>
         {css_styles}
     </style>
-    {stars_text_node}
-    {commits_text_node}
+    {text_nodes}
 </svg>"#
     );
 
@@ -131,7 +172,7 @@ mod tests {
     #[test]
     fn render_stats_produces_svg() {
         let theme = crate::themes::dark();
-        let part = render_stats(&theme, 42, 1234, 250.0);
+        let part = render_stats(&theme, 42, 1234, None, 250.0);
         assert!(part.content.contains("<svg"));
         assert!(part.content.contains("42"));
         assert!(part.content.contains("1.2k"));
@@ -141,15 +182,39 @@ mod tests {
     #[test]
     fn render_stats_right_aligns_values() {
         let theme = crate::themes::dark();
-        let part = render_stats(&theme, 10, 20, 250.0);
+        let part = render_stats(&theme, 10, 20, None, 250.0);
         assert!(part.content.contains("text-anchor='end'"));
         assert!(part.content.contains("x='250'"));
     }
 
+    #[test]
+    fn render_stats_commits_year_label() {
+        let theme = crate::themes::dark();
+        let part = render_stats(&theme, 1, 2, Some(2025), 250.0);
+        assert!(part.content.contains("Commits in 2025"));
+        assert!(!part.content.contains("Total Commits"));
+    }
+
+    #[test]
+    fn render_window_stats_rows() {
+        let theme = crate::themes::dark();
+        let window_stats = WindowStats {
+            since: "2025-01-01".to_string(),
+            until: "2025-12-31".to_string(),
+            commits: 120,
+            pull_requests: 7,
+            contributions: 2500,
+        };
+        let part = render_window_stats(&theme, &window_stats, 250.0);
+        assert!(part.content.contains("Pull Requests"));
+        assert!(part.content.contains("2.5k"));
+        assert_eq!(part.height, 3.0 * ROW_HEIGHT + 2.0 * ROW_GAP);
+    }
+
     #[test]
     fn render_stats_label_value_classes() {
         let theme = crate::themes::dark();
-        let part = render_stats(&theme, 1, 2, 250.0);
+        let part = render_stats(&theme, 1, 2, None, 250.0);
         assert!(part.content.contains("stat-label"));
         assert!(part.content.contains("stat-value"));
     }
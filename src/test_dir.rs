@@ -0,0 +1,27 @@
+//! Scratch directories for tests that touch the filesystem.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// An empty directory under the system temp dir, named after the test so that tests
+/// running in parallel don't share it, and removed when dropped, even if the test fails.
+pub struct TestDir(PathBuf);
+
+impl TestDir {
+    pub fn new(test: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("github-stats-{test}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TestDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
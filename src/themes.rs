@@ -1,5 +1,12 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
 use log::warn;
+use serde::Deserialize;
 
+/// Card colors. Colors a custom theme leaves out are taken from the dark theme.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Theme {
     pub title_color: String,
     pub icon_color: String,
@@ -8,6 +15,65 @@ pub struct Theme {
     pub border_color: String,
 }
 
+/// Names of the built-in themes.
+pub const NAMES: [&str; 2] = ["dark", "light"];
+
+impl Default for Theme {
+    fn default() -> Self {
+        dark()
+    }
+}
+
+impl Theme {
+    /// The first color that is not a plain CSS color value, as `(field, value)`.
+    ///
+    /// Colors are written into SVG attributes and styles unescaped, so anything beyond
+    /// hex codes, names and functional notation such as `rgb(…)` is rejected.
+    pub fn invalid_color(&self) -> Option<(&'static str, &str)> {
+        [
+            ("title_color", &self.title_color),
+            ("icon_color", &self.icon_color),
+            ("text_color", &self.text_color),
+            ("background_color", &self.background_color),
+            ("border_color", &self.border_color),
+        ]
+        .into_iter()
+        .find(|(_, color)| {
+            color.is_empty()
+                || !color
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "#(),.% ".contains(c))
+        })
+        .map(|(field, color)| (field, color.as_str()))
+    }
+}
+
+/// Themes defined in the config file, shared by all workers.
+#[cfg(not(test))]
+fn custom_themes() -> &'static Mutex<HashMap<String, Theme>> {
+    static CUSTOM: std::sync::OnceLock<Mutex<HashMap<String, Theme>>> = std::sync::OnceLock::new();
+    CUSTOM.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Per test thread, so tests registering themes in parallel don't replace each other's.
+#[cfg(test)]
+fn custom_themes() -> &'static Mutex<HashMap<String, Theme>> {
+    thread_local! {
+        static CUSTOM: &'static Mutex<HashMap<String, Theme>> =
+            Box::leak(Box::new(Mutex::new(HashMap::new())));
+    }
+    CUSTOM.with(|custom| *custom)
+}
+
+/// Makes `themes` available by name, in addition to (or replacing) the built-in ones.
+pub fn register(themes: &HashMap<String, Theme>) {
+    *custom_themes().lock().unwrap() = themes.clone();
+}
+
+pub fn exists(name: &str) -> bool {
+    NAMES.contains(&name) || custom_themes().lock().unwrap().contains_key(name)
+}
+
 pub fn dark() -> Theme {
     Theme {
         title_color: String::from("#fff"),
@@ -30,6 +96,9 @@ pub fn light() -> Theme {
 
 /// Returns the theme matching `name`, falling back to dark for unknown names.
 pub fn from_name(name: &str) -> Theme {
+    if let Some(theme) = custom_themes().lock().unwrap().get(name) {
+        return theme.clone();
+    }
     match name {
         "dark" => dark(),
         "light" => light(),
@@ -73,4 +142,29 @@ mod tests {
         let theme = from_name("nonexistent");
         assert_eq!(theme.background_color, "#151515");
     }
+
+    #[test]
+    fn registered_theme_is_found_by_name() {
+        let ocean = Theme {
+            background_color: String::from("#002b36"),
+            ..Theme::default()
+        };
+        register(&HashMap::from([(String::from("test-ocean"), ocean)]));
+        assert!(exists("test-ocean"));
+        assert_eq!(from_name("test-ocean").background_color, "#002b36");
+        assert_eq!(from_name("test-ocean").title_color, dark().title_color);
+    }
+
+    #[test]
+    fn invalid_color_rejects_markup() {
+        let mut theme = light();
+        assert_eq!(theme.invalid_color(), None);
+        theme.text_color = String::from("rgb(10, 20, 30)");
+        assert_eq!(theme.invalid_color(), None);
+        theme.border_color = String::from("red' onload='x");
+        assert_eq!(
+            theme.invalid_color(),
+            Some(("border_color", "red' onload='x"))
+        );
+    }
 }
@@ -0,0 +1,83 @@
+use sha2::{Digest, Sha256};
+
+use crate::rate_limit;
+
+/// The first token whose `resource` budget is not known to be exhausted.
+pub fn available<'a>(tokens: &'a [String], resource: &str) -> Option<&'a str> {
+    pick_available(tokens, |t| rate_limit::is_exhausted(&key(t), resource))
+}
+
+/// The token to use next: the first one with budget left, otherwise the one that resets first.
+pub fn select<'a>(tokens: &'a [String], resource: &str) -> Option<&'a str> {
+    available(tokens, resource).or_else(|| {
+        tokens
+            .iter()
+            .min_by_key(|t| rate_limit::next_reset(&[key(t)], resource))
+            .map(String::as_str)
+    })
+}
+
+fn pick_available(tokens: &[String], is_exhausted: impl Fn(&str) -> bool) -> Option<&str> {
+    tokens.iter().map(String::as_str).find(|t| !is_exhausted(t))
+}
+
+/// Identifies a token in shared state without keeping the token itself. Unlike [`mask`],
+/// it is distinct for every token.
+pub fn key(token: &str) -> String {
+    let digest = Sha256::digest(token.as_bytes());
+    let hex: String = digest.iter().map(|b| format!("{b:02x}")).collect();
+    format!("token {hex}")
+}
+
+/// Keeps just enough of a token to tell configured tokens apart in logs and `/health`.
+pub fn mask(token: &str) -> String {
+    let chars: Vec<char> = token.chars().collect();
+    if chars.len() <= 8 {
+        return "…".to_string();
+    }
+    let suffix: String = chars[chars.len() - 4..].iter().collect();
+    format!("…{suffix}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pick_available_skips_exhausted_tokens() {
+        let tokens: Vec<String> = ["a", "b", "c"].iter().map(|t| t.to_string()).collect();
+        assert_eq!(pick_available(&tokens, |_| false), Some("a"));
+        assert_eq!(pick_available(&tokens, |t| t == "a"), Some("b"));
+        assert_eq!(pick_available(&tokens, |_| true), None);
+    }
+
+    #[test]
+    fn mask_keeps_only_suffix() {
+        assert_eq!(mask("ghp_abcdefghijklmnop"), "…mnop");
+        assert_eq!(mask("short"), "…");
+    }
+
+    #[test]
+    fn tokens_with_the_same_mask_are_tracked_apart() {
+        let tokens: Vec<String> = ["pool-a", "pool-b"].iter().map(|t| t.to_string()).collect();
+        assert_eq!(mask(&tokens[0]), mask(&tokens[1]));
+        let reset = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 3600;
+        rate_limit::record(
+            &key(&tokens[0]),
+            &mask(&tokens[0]),
+            "core",
+            rate_limit::Budget {
+                remaining: Some(0),
+                limit: Some(5000),
+                used: Some(5000),
+                reset: Some(reset),
+            },
+        );
+        assert_eq!(available(&tokens, "core"), Some("pool-b"));
+        assert_eq!(rate_limit::remaining(&[key(&tokens[1])], "core"), None);
+    }
+}
@@ -0,0 +1,136 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use actix_web::web::Data;
+use hmac::{Hmac, Mac};
+use log::{error, info};
+use serde::Deserialize;
+use sha2::Sha256;
+
+use crate::reload::SharedConfig;
+use crate::{crawl_stats, remove_window_caches};
+
+/// Whether a refresh is waiting for its debounce delay to pass.
+static SCHEDULED: AtomicBool = AtomicBool::new(false);
+
+/// Events that can change the numbers on a card.
+pub fn triggers_refresh(event: &str) -> bool {
+    matches!(event, "push" | "star" | "repository" | "public")
+}
+
+/// Checks an `X-Hub-Signature-256` header (`sha256=<hex>`) against the payload, in
+/// constant time.
+pub fn signature_valid(secret: &str, payload: &[u8], header: Option<&str>) -> bool {
+    let Some(signature) = header
+        .and_then(|h| h.strip_prefix("sha256="))
+        .and_then(decode_hex)
+    else {
+        return false;
+    };
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(payload);
+    mac.verify_slice(&signature).is_ok()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct Account {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct Repository {
+    owner: Account,
+}
+
+/// The parts of an event payload that say whose stats it affects.
+#[derive(Deserialize)]
+struct Payload {
+    repository: Option<Repository>,
+    sender: Option<Account>,
+}
+
+/// Whether an event is about `user`: one of their repositories, or something they did,
+/// such as a push to an organization repository.
+pub fn concerns_user(payload: &[u8], user: &str) -> Result<bool, serde_json::Error> {
+    let payload: Payload = serde_json::from_slice(payload)?;
+    let owner = payload.repository.map(|r| r.owner.login);
+    let sender = payload.sender.map(|s| s.login);
+    Ok([owner, sender]
+        .into_iter()
+        .flatten()
+        .any(|login| login.eq_ignore_ascii_case(user)))
+}
+
+/// Schedules a refresh `delay` from now, unless one is already waiting, and returns
+/// whether this call scheduled it. Events during the wait are covered by that refresh.
+pub fn schedule_refresh(config: Data<SharedConfig>, delay: Duration) -> bool {
+    if SCHEDULED.swap(true, Ordering::AcqRel) {
+        return false;
+    }
+    actix_web::rt::spawn(async move {
+        actix_web::rt::time::sleep(delay).await;
+        // Cleared before crawling, so events that arrive during the crawl schedule
+        // another refresh instead of being lost.
+        SCHEDULED.store(false, Ordering::Release);
+        refresh(config).await;
+    });
+    true
+}
+
+/// Crawls without marking the cache stale first, so cards keep being served from it
+/// during the crawl, and still are if the crawl fails.
+async fn refresh(config: Data<SharedConfig>) {
+    let config = config.current();
+    match crawl_stats(&config).await {
+        Ok(_) => {
+            remove_window_caches(&config.cache_path);
+            info!("Stats refreshed after GitHub webhook events");
+        }
+        Err(err) => error!("Failed to refresh stats after GitHub webhook events: {err:#}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_matches_github_example() {
+        // From GitHub's documentation on validating webhook deliveries.
+        let secret = "It's a Secret to Everybody";
+        let signature = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+        assert!(signature_valid(secret, b"Hello, World!", Some(signature)));
+        assert!(!signature_valid(secret, b"Hello, World?", Some(signature)));
+        assert!(!signature_valid("other", b"Hello, World!", Some(signature)));
+        assert!(!signature_valid(
+            secret,
+            b"Hello, World!",
+            Some("sha256=75")
+        ));
+        assert!(!signature_valid(secret, b"Hello, World!", Some("sha1=abc")));
+        assert!(!signature_valid(secret, b"Hello, World!", None));
+    }
+
+    #[test]
+    fn events_are_matched_to_the_user() {
+        let star = br#"{"action":"created","repository":{"owner":{"login":"Octo"}},"sender":{"login":"fan"}}"#;
+        assert!(concerns_user(star, "octo").unwrap());
+        let org_push = br#"{"repository":{"owner":{"login":"acme"}},"sender":{"login":"octo"}}"#;
+        assert!(concerns_user(org_push, "octo").unwrap());
+        let other = br#"{"repository":{"owner":{"login":"acme"}},"sender":{"login":"fan"}}"#;
+        assert!(!concerns_user(other, "octo").unwrap());
+        assert!(concerns_user(b"not json", "octo").is_err());
+        assert!(triggers_refresh("push") && !triggers_refresh("issues"));
+    }
+}